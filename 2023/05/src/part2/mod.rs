@@ -1,9 +1,14 @@
 use crate::puzzle::Puzzle;
+use interval::IntervalSet;
 
 pub fn run(input: &str) -> u64 {
     let puzzle: Puzzle = input.parse().expect("parse failed");
     let (intervals, _) = puzzle.seeds.as_chunks();
-    puzzle.lookup_min(intervals.into_iter().cloned())
+    let seeds: IntervalSet<u64> = intervals
+        .iter()
+        .map(|&[source, len]| source..source + len)
+        .collect();
+    puzzle.composed_map().image(&seeds).min().unwrap()
 }
 
 #[cfg(test)]
@@ -1,3 +1,4 @@
+use interval::PiecewiseMap;
 use parse_display::{Display, FromStr};
 use parse_display_with::formats::delimiter;
 
@@ -18,6 +19,12 @@ impl Puzzle {
             .min()
             .unwrap()
     }
+
+    pub fn composed_map(&self) -> PiecewiseMap<u64> {
+        self.maps.iter().fold(PiecewiseMap::new(), |composed, map| {
+            composed.then(&map.piecewise())
+        })
+    }
 }
 
 #[derive(Clone, Debug, Display, FromStr)]
@@ -36,6 +43,13 @@ impl Map {
         Self { label, entries }
     }
 
+    fn piecewise(&self) -> PiecewiseMap<u64> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.source..entry.source + entry.len, entry.dest))
+            .collect()
+    }
+
     fn lookup_interval(&self, source: u64, len: u64) -> (u64, u64) {
         let idx = self
             .entries
@@ -1,15 +1,13 @@
-use crate::nodes::Graph;
+use crate::nodes::Name;
 use crate::puzzle::Puzzle;
 use crate::state::State;
 
 pub fn run(input: &str) -> usize {
     let puzzle: Puzzle = input.parse().expect("parse failed");
-    let graph = Graph::new();
-    puzzle.add_to_graph(&graph);
-    let start = "AAA".parse().unwrap();
-    let target = "ZZZ".parse().unwrap();
-    let mut state = State::new(&puzzle.instructions, &graph.nodes[&start]);
-    state.target_distance(|node| node.name == target)
+    let start = puzzle.nodes.id(&"AAA".parse().unwrap()).unwrap();
+    let target: Name = "ZZZ".parse().unwrap();
+    let mut state = State::new(&puzzle.instructions, &puzzle.nodes, start);
+    state.target_distance(|name| name == target)
 }
 
 #[cfg(test)]
@@ -1,21 +1,19 @@
-use crate::nodes::Graph;
 use crate::puzzle::Puzzle;
 use crate::state::State;
 use num_integer::lcm;
 
 pub fn run(input: &str) -> usize {
     let puzzle: Puzzle = input.parse().expect("parse failed");
-    let graph = Graph::new();
-    graph.add_nodes(&puzzle.nodes);
     puzzle
         .nodes
-        .0
-        .keys()
-        .filter(|name| name.is_source())
-        .map(|name| {
-            let cnode = &graph.nodes[name];
-            let mut state = State::new(&puzzle.instructions, &cnode);
-            state.find(|cnode| cnode.is_target()).unwrap()
+        .nodes()
+        .names()
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.is_source())
+        .map(|(node, _)| {
+            let mut state = State::new(&puzzle.instructions, &puzzle.nodes, node);
+            state.find(|name| name.is_target()).unwrap()
         })
         .fold(1, lcm)
 }
@@ -1,12 +1,12 @@
 use crate::instructions::Instructions;
-use crate::nodes::{Graph, Nodes};
+use crate::nodes::Nodes;
 use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct ParseError;
 
-impl From<<Nodes as FromStr>::Err> for ParseError {
-    fn from(_value: <Nodes as FromStr>::Err) -> Self {
+impl From<graph::ParseError> for ParseError {
+    fn from(_value: graph::ParseError) -> Self {
         Self {}
     }
 }
@@ -29,10 +29,6 @@ impl Puzzle {
             nodes,
         }
     }
-
-    pub fn add_to_graph<'arena>(&self, graph: &'arena Graph<'arena>) {
-        graph.add_nodes(&self.nodes);
-    }
 }
 
 impl FromStr for Puzzle {
@@ -1,63 +1,81 @@
 use crate::direction::Direction;
 use crate::instructions::Instructions;
-use crate::nodes::CyclicNodeRef;
-use crate::nodes::Name;
-use std::collections::HashSet;
+use crate::nodes::{Name, Nodes};
+use graph::NodeId;
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct State<'a> {
     instructions: &'a [Direction],
+    nodes: &'a Nodes,
     pos: usize, // position in the instructions list
-    cnode: CyclicNodeRef<'a>,
+    node: NodeId,
 }
 
 impl<'a> State<'a> {
-    pub fn new(instructions: &'a Instructions, cnode: CyclicNodeRef<'a>) -> Self {
+    pub fn new(instructions: &'a Instructions, nodes: &'a Nodes, node: NodeId) -> Self {
         Self {
             instructions: &instructions.0,
+            nodes,
             pos: 0,
-            cnode,
+            node,
         }
     }
 
     fn step(&mut self) {
         let dir = self.instructions[self.pos];
         self.pos = (self.pos + 1) % self.instructions.len();
+        let &[l, r] = self.nodes.successors(self.node) else {
+            panic!(
+                "node {} does not have two exits",
+                self.nodes.name(self.node)
+            );
+        };
         use Direction::*;
-        self.cnode = match dir {
-            Left => self.cnode.l.get().unwrap(),
-            Right => self.cnode.r.get().unwrap(),
+        self.node = match dir {
+            Left => l,
+            Right => r,
         }
     }
 
     // Step to the next target node and return the # of steps taken.
-    pub fn target_distance(&mut self, is_target: impl Fn(CyclicNodeRef) -> bool) -> usize {
+    pub fn target_distance(&mut self, is_target: impl Fn(Name) -> bool) -> usize {
         let mut count = 0usize;
         loop {
             self.step();
             count += 1;
-            if is_target(self.cnode) {
+            if is_target(*self.nodes.name(self.node)) {
                 break count;
             }
         }
     }
 
-    fn location(&self) -> (usize, Name) {
-        (self.pos, self.cnode.name)
+    fn location(&self) -> (usize, NodeId) {
+        (self.pos, self.node)
     }
 
     // Find the distances to each target node, ending when we find a loop.
-    pub fn find(&mut self, is_target: impl Fn(CyclicNodeRef) -> bool) -> Option<usize> {
-        let mut seen: HashSet<(usize, Name)> = HashSet::new();
+    pub fn find(&mut self, is_target: impl Fn(Name) -> bool) -> Option<usize> {
         let distance = self.target_distance(&is_target);
-        loop {
-            if seen.contains(&self.location()) {
-                break Some(distance);
-            }
-            seen.insert(self.location());
-            if self.target_distance(&is_target) != distance {
-                break None;
-            }
-        }
+        let mut uniform = true;
+        cycle::hashed(self.clone(), |mut state| {
+            uniform &= state.target_distance(&is_target) == distance;
+            state
+        });
+        uniform.then_some(distance)
+    }
+}
+
+impl PartialEq for State<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.location() == other.location()
+    }
+}
+
+impl Eq for State<'_> {}
+
+impl Hash for State<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.location().hash(state);
     }
 }
@@ -57,16 +57,15 @@ impl GuardState {
         };
         true
     }
-    fn march(&mut self, mut command: impl FnMut(&GuardState) -> Command) -> MarchResult {
-        let mut previous_states: HashSet<GuardState> = HashSet::new();
-        loop {
-            if previous_states.contains(&self) {
-                return MarchResult::Loop;
-            }
-            previous_states.insert(self.clone());
-            if !self.step(&mut command) {
-                return MarchResult::Halt;
-            }
+    fn march(&self, mut command: impl FnMut(&GuardState) -> Command) -> MarchResult {
+        // Halting is modelled as a final `None` state that steps to itself.
+        let cycle = cycle::hashed(Some(self.clone()), |state| {
+            let mut state = state?;
+            state.step(&mut command).then_some(state)
+        });
+        match cycle.entry {
+            Some(_) => MarchResult::Loop,
+            None => MarchResult::Halt,
         }
     }
 }
@@ -98,7 +97,7 @@ fn guard_algorithm(map: &Map) -> impl Fn(&GuardState) -> Command {
 fn part1(input: &str) -> usize {
     let map = input.parse().expect("Parse failed.");
     let mut visited = HashSet::<Position>::new();
-    let mut state = GuardState::new(&map);
+    let state = GuardState::new(&map);
     let algorithm = guard_algorithm(&map);
     state.march(|state| {
         visited.insert(state.pos);
@@ -109,7 +108,7 @@ fn part1(input: &str) -> usize {
 
 fn part2(input: &str) -> usize {
     let map = input.parse().expect("Parse failed.");
-    let mut ghost = GuardState::new(&map);
+    let ghost = GuardState::new(&map);
     let start = ghost.pos;
     let algorithm = guard_algorithm(&map);
     let mut count = 0usize;
@@ -117,7 +116,6 @@ fn part2(input: &str) -> usize {
         let mut next_state = state.clone();
         if next_state.step(&algorithm) && next_state.pos != start {
             let obstacle = next_state.pos;
-            let mut state = state.clone();
             if state.march(|state| {
                 if state.pos + state.dir == obstacle {
                     Command::Right
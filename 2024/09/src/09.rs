@@ -1,231 +1,23 @@
-#[derive(Clone, Debug)]
-enum Block {
-    File(usize),
-    Free,
-}
-
-#[derive(Clone, Debug)]
-struct Span {
-    pos: usize,
-    size: usize,
-    block: Block,
-}
-
-impl Span {
-    fn is_free(&self) -> bool {
-        match *self {
-            Span {
-                pos: _,
-                size,
-                block: Block::Free,
-            } if size > 0 => true,
-            _ => false,
-        }
-    }
-
-    fn is_file(&self) -> bool {
-        match *self {
-            Span {
-                pos: _,
-                size: _,
-                block: Block::File(_),
-            } => true,
-            _ => false,
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Disk(Vec<Span>);
-
-impl Disk {
-    fn files_iter(&self) -> impl Iterator<Item = &Span> {
-        self.0.iter().rev().filter(|span| span.is_file())
-    }
-
-    fn checksum(&self) -> usize {
-        return {
-            self.0
-                .iter()
-                .map(|Span { pos, size, block }| match block {
-                    Block::File(id) => size * (2 * pos + size - 1) / 2 * id,
-                    Block::Free => 0,
-                })
-                .sum()
-        };
-    }
-}
-
-impl std::str::FromStr for Disk {
-    type Err = std::num::ParseIntError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut fileid = 0usize;
-        let mut spans = Vec::<Span>::new();
-        let mut file = true;
-        let mut pos = 0usize;
-        for ch in s.chars() {
-            let st: String = ch.into();
-            let size: usize = st.parse()?;
-            let block = if file {
-                let block = Block::File(fileid);
-                fileid += 1;
-                block
-            } else {
-                Block::Free
-            };
-            spans.push(Span { pos, size, block });
-            pos += size;
-            file = !file;
-        }
-        Ok(Disk(spans))
-    }
-}
-
-trait Allocator {
-    fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span>;
-}
-
-trait Part {
-    fn allocator(disk: &Disk) -> impl Allocator;
-
-    fn defrag(disk: &mut Disk) {
-        let mut spans = vec![];
-        {
-            let mut freelist = Self::allocator(disk);
-            for file in disk.files_iter() {
-                let mut file = file.clone();
-                let mut free_spans = freelist.alloc(&mut file.size, file.pos);
-                for span in &mut free_spans {
-                    span.block = file.block.clone();
-                }
-                spans.append(&mut free_spans);
-                if file.size > 0 {
-                    spans.push(file);
-                }
-            }
-        }
-        disk.0 = spans;
-    }
-
-    fn run(input: &str) -> usize {
-        let mut disk: Disk = input.parse().expect("Parsing error.");
-        // dbg!(&disk);
-        Self::defrag(&mut disk);
-        // dbg!(&disk);
-        disk.checksum()
-    }
-}
-
-mod part1 {
-    pub enum Impl {}
-
-    struct Allocator {
-        spans: Vec<super::Span>,
-    }
-
-    impl Allocator {
-        fn new(disk: &super::Disk) -> Self {
-            Self {
-                spans: disk
-                    .0
-                    .iter()
-                    .filter(|span| span.is_free())
-                    .rev()
-                    .cloned()
-                    .collect(),
-            }
-        }
-    }
-
-    impl super::Allocator for Allocator {
-        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<super::Span> {
-            let mut spans = vec![];
-            while *size > 0 {
-                if let Some(mut free) = self.spans.pop() {
-                    if free.pos >= max_pos {
-                        break;
-                    }
-                    let mut span = free.clone();
-                    if span.size > *size {
-                        span.size = *size;
-                    };
-                    free.size -= span.size;
-                    free.pos += span.size;
-                    *size -= span.size;
-                    spans.push(span);
-                    if free.size > 0 {
-                        self.spans.push(free);
-                    }
-                } else {
-                    break;
-                }
-            }
-            spans
-        }
-    }
-
-    impl super::Part for Impl {
-        fn allocator(disk: &super::Disk) -> impl super::Allocator {
-            Allocator::new(disk)
-        }
-    }
-}
-
-mod part2 {
-    pub enum Impl {}
-
-    struct Allocator {
-        spans: Vec<super::Span>,
-    }
-
-    impl Allocator {
-        fn new(disk: &super::Disk) -> Self {
-            Self {
-                spans: disk
-                    .0
-                    .iter()
-                    .filter(|span| span.is_free())
-                    .cloned()
-                    .collect(),
-            }
-        }
-    }
-
-    impl super::Allocator for Allocator {
-        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<super::Span> {
-            match self
-                .spans
-                .iter_mut()
-                .take_while(|s| s.pos < max_pos)
-                .find(|s| s.size >= *size)
-            {
-                Some(free) => {
-                    let mut span = free.clone();
-                    span.size = *size;
-                    free.pos += span.size;
-                    free.size -= span.size;
-                    *size = 0;
-                    vec![span]
-                }
-                None => vec![],
-            }
-        }
-    }
-
-    impl super::Part for Impl {
-        fn allocator(disk: &super::Disk) -> impl super::Allocator {
-            Allocator::new(disk)
-        }
-    }
+mod disk;
+mod strategy;
+mod synthetic;
+
+use disk::Disk;
+use strategy::{Compaction, End, Strategy};
+
+fn run(input: &str, strategy: Strategy) -> usize {
+    let disk: Disk = input.parse().expect("Parsing error.");
+    Compaction::new(&disk, strategy, End::Start)
+        .finish()
+        .checksum()
 }
 
 fn part1(input: &str) -> usize {
-    part1::Impl::run(input)
+    run(input, Strategy::Fragment)
 }
 
 fn part2(input: &str) -> usize {
-    part2::Impl::run(input)
+    run(input, Strategy::FirstFit)
 }
 
 #[cfg(test)]
@@ -247,6 +39,65 @@ mod tests {
 
 fn main() {
     let input = include_str!("../data/actual/input");
-    println!("Part 1: {}", part1(input));
-    println!("Part 2: {}", part2(input));
+    let arg = |n: usize| std::env::args().nth(n);
+    match arg(1).as_deref() {
+        // Compact a disk map from stdin with a strategy, towards `start` or
+        // `end`, and draw the disk after each move.
+        Some("compact") => {
+            let strategy: Strategy = arg(2)
+                .map_or(Ok(Strategy::FirstFit), |s| s.parse())
+                .expect("unknown strategy");
+            let towards: End = arg(3)
+                .map_or(Ok(End::Start), |s| s.parse())
+                .expect("unknown end");
+            let input = std::io::read_to_string(std::io::stdin()).expect("read failed");
+            let disk: Disk = input.parse().expect("Parsing error.");
+            if disk.is_empty() {
+                eprintln!("empty disk map");
+                std::process::exit(1);
+            }
+            println!("{disk}");
+            let mut compaction = Compaction::new(&disk, strategy, towards);
+            while let Some(step) = compaction.next() {
+                println!("{}  {step}", compaction.disk());
+            }
+            let disk = compaction.disk();
+            println!("{}", disk.fragmentation());
+            println!("checksum {}", disk.checksum());
+        }
+        // Compare every strategy on the puzzle input.
+        Some("strategies") => {
+            let disk: Disk = input.parse().expect("Parsing error.");
+            println!("before: {}", disk.fragmentation());
+            for strategy in Strategy::ALL {
+                for towards in [End::Start, End::End] {
+                    let disk = Compaction::new(&disk, strategy, towards).finish();
+                    println!(
+                        "{strategy} towards {towards}: checksum {}, {}",
+                        disk.checksum(),
+                        disk.fragmentation()
+                    );
+                }
+            }
+        }
+        // Time first-fit compaction with and without the free space index on
+        // a random disk map with the given number of files.
+        Some("bench") => {
+            let files: usize = arg(2).map_or(50_000, |n| n.parse().expect("bad count"));
+            let disk: Disk = synthetic::disk_map(files, 0)
+                .parse()
+                .expect("Parsing error.");
+            for strategy in [Strategy::FirstFit, Strategy::FirstFitScan] {
+                let start = std::time::Instant::now();
+                let checksum = Compaction::new(&disk, strategy, End::Start)
+                    .finish()
+                    .checksum();
+                println!("{strategy}: checksum {checksum} in {:?}", start.elapsed());
+            }
+        }
+        _ => {
+            println!("Part 1: {}", part1(input));
+            println!("Part 2: {}", part2(input));
+        }
+    }
 }
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Block {
+    File(usize),
+    Free,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub pos: usize,
+    pub size: usize,
+    pub block: Block,
+}
+
+impl Span {
+    pub fn is_free(&self) -> bool {
+        matches!(self.block, Block::Free) && self.size > 0
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self.block, Block::File(_))
+    }
+
+    pub fn end(&self) -> usize {
+        self.pos + self.size
+    }
+}
+
+// The spans of a disk in order, with free spans filling every gap between
+// files up to the end of the disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disk(pub Vec<Span>);
+
+impl Disk {
+    // Lay out file spans on a disk of `len` blocks. Empty spans are dropped.
+    pub fn layout(files: impl IntoIterator<Item = Span>, len: usize) -> Self {
+        let mut files: Vec<Span> = (files.into_iter())
+            .filter(|span| span.is_file() && span.size > 0)
+            .collect();
+        files.sort_by_key(|span| span.pos);
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        for file in files.into_iter().chain([Span {
+            pos: len,
+            size: 0,
+            block: Block::Free,
+        }]) {
+            if file.pos > pos {
+                spans.push(Span {
+                    pos,
+                    size: file.pos - pos,
+                    block: Block::Free,
+                });
+            }
+            pos = file.end();
+            if file.is_file() {
+                spans.push(file);
+            }
+        }
+        Disk(spans)
+    }
+
+    pub fn files_iter(&self) -> impl Iterator<Item = &Span> {
+        self.0.iter().rev().filter(|span| span.is_file())
+    }
+
+    // The number of blocks.
+    pub fn len(&self) -> usize {
+        self.0.last().map_or(0, Span::end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The same disk read from the other end.
+    pub fn mirror(&self) -> Self {
+        let len = self.len();
+        Disk(
+            (self.0.iter().rev())
+                .map(|span| Span {
+                    pos: len - span.end(),
+                    ..span.clone()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn checksum(&self) -> usize {
+        self.0
+            .iter()
+            .map(|Span { pos, size, block }| match block {
+                Block::File(id) => size * (2 * pos + size - 1) / 2 * id,
+                Block::Free => 0,
+            })
+            .sum()
+    }
+
+    pub fn fragmentation(&self) -> Fragmentation {
+        let mut pieces: HashMap<usize, usize> = HashMap::new();
+        for span in &self.0 {
+            if let Block::File(id) = span.block {
+                *pieces.entry(id).or_default() += 1;
+            }
+        }
+        let first = self.0.iter().position(Span::is_file).unwrap_or(0);
+        let last = self.0.iter().rposition(Span::is_file).unwrap_or(0);
+        let holes: Vec<usize> = (self.0[first..last].iter())
+            .filter(|span| span.is_free())
+            .map(|span| span.size)
+            .collect();
+        Fragmentation {
+            files: pieces.len(),
+            pieces: pieces.values().sum(),
+            split_files: pieces.values().filter(|&&count| count > 1).count(),
+            free: holes.iter().sum(),
+            holes: holes.len(),
+            largest_hole: holes.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+// How scattered the files and the free space between them are. Free space
+// before the first file or after the last doesn't count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fragmentation {
+    pub files: usize,
+    pub pieces: usize,
+    pub split_files: usize,
+    pub free: usize,
+    pub holes: usize,
+    pub largest_hole: usize,
+}
+
+impl Display for Fragmentation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} files in {} pieces ({} split), {} free blocks in {} holes (largest {})",
+            self.files, self.pieces, self.split_files, self.free, self.holes, self.largest_hole
+        )
+    }
+}
+
+impl std::str::FromStr for Disk {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fileid = 0usize;
+        let mut spans = Vec::<Span>::new();
+        let mut file = true;
+        let mut pos = 0usize;
+        for ch in s.trim_end().chars() {
+            let st: String = ch.into();
+            let size: usize = st.parse()?;
+            let block = if file {
+                let block = Block::File(fileid);
+                fileid += 1;
+                block
+            } else {
+                Block::Free
+            };
+            spans.push(Span { pos, size, block });
+            pos += size;
+            file = !file;
+        }
+        Ok(Disk(spans))
+    }
+}
+
+// The blocks as the puzzle draws them, with `.` for free space. File ids
+// past 9 only show their last digit.
+impl Display for Disk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for span in &self.0 {
+            let c = match span.block {
+                Block::File(id) => char::from(b'0' + (id % 10) as u8),
+                Block::Free => '.',
+            };
+            for _ in 0..span.size {
+                f.write_char(c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let disk: Disk = "12345".parse().unwrap();
+        assert_eq!(disk.to_string(), "0..111....22222");
+        assert_eq!(disk.mirror().to_string(), "22222....111..0");
+        assert_eq!(disk.mirror().mirror(), disk);
+    }
+
+    #[test]
+    fn test_layout() {
+        let disk: Disk = "12345".parse().unwrap();
+        let files: Vec<Span> = disk.files_iter().cloned().collect();
+        assert_eq!(
+            Disk::layout(files, disk.len()).to_string(),
+            "0..111....22222"
+        );
+        assert_eq!(disk.len(), 15);
+        assert!(!disk.is_empty());
+        assert!(Disk::layout([], 0).is_empty());
+        assert!(disk.0[1].is_free());
+        let empty = Span {
+            pos: 0,
+            size: 0,
+            block: Block::Free,
+        };
+        assert!(!empty.is_free() && !empty.is_file());
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let disk: Disk = "2333133121414131402".parse().unwrap();
+        let fragmentation = disk.fragmentation();
+        assert_eq!(fragmentation.files, 10);
+        assert_eq!(fragmentation.pieces, 10);
+        assert_eq!(fragmentation.free, 14);
+        assert_eq!(fragmentation.holes, 8);
+        assert_eq!(fragmentation.largest_hole, 3);
+    }
+}
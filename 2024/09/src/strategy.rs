@@ -0,0 +1,432 @@
+use crate::disk::{Block, Disk, Span};
+use parse_display::{Display, FromStr};
+
+pub trait Allocator {
+    // Free spans below `max_pos` for up to `size` blocks of a file, taking
+    // off `size` what they hold.
+    fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, FromStr)]
+#[display(style = "kebab-case")]
+pub enum Strategy {
+    // Fill the first free blocks, splitting files as needed (part 1).
+    Fragment,
+    // Move whole files to the first gap they fit (part 2).
+    FirstFit,
+    // The same as `FirstFit` by scanning every gap, for comparison.
+    FirstFitScan,
+    // Move whole files to the smallest gap they fit.
+    BestFit,
+    // Move whole files to the largest gap.
+    WorstFit,
+    // Move whole files to the next gap they fit after the last one used.
+    NextFit,
+}
+
+impl Strategy {
+    // The strategies with different results.
+    pub const ALL: [Strategy; 5] = [
+        Strategy::Fragment,
+        Strategy::FirstFit,
+        Strategy::BestFit,
+        Strategy::WorstFit,
+        Strategy::NextFit,
+    ];
+
+    pub fn allocator(self, disk: &Disk) -> Box<dyn Allocator> {
+        let spans = disk.0.iter().filter(|span| span.is_free()).cloned();
+        match self {
+            Strategy::Fragment => Box::new(fragment::Allocator::new(spans)),
+            Strategy::FirstFit => Box::new(first_fit::Allocator::new(spans)),
+            Strategy::FirstFitScan => Box::new(fit::Allocator::new(spans, fit::first)),
+            Strategy::BestFit => Box::new(fit::Allocator::new(spans, fit::best)),
+            Strategy::WorstFit => Box::new(fit::Allocator::new(spans, fit::worst)),
+            Strategy::NextFit => Box::new(next_fit::Allocator::new(spans)),
+        }
+    }
+}
+
+mod fragment {
+    use crate::disk::Span;
+
+    pub struct Allocator {
+        spans: Vec<Span>,
+    }
+
+    impl Allocator {
+        pub fn new(spans: impl DoubleEndedIterator<Item = Span>) -> Self {
+            Self {
+                spans: spans.rev().collect(),
+            }
+        }
+    }
+
+    impl super::Allocator for Allocator {
+        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span> {
+            let mut spans = vec![];
+            while *size > 0 {
+                if let Some(mut free) = self.spans.pop() {
+                    if free.pos >= max_pos {
+                        break;
+                    }
+                    let mut span = free.clone();
+                    if span.size > *size {
+                        span.size = *size;
+                    };
+                    free.size -= span.size;
+                    free.pos += span.size;
+                    *size -= span.size;
+                    spans.push(span);
+                    if free.size > 0 {
+                        self.spans.push(free);
+                    }
+                } else {
+                    break;
+                }
+            }
+            spans
+        }
+    }
+}
+
+mod first_fit {
+    use crate::disk::{Block, Span};
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    // The positions of the free spans of each size, lowest first. The first
+    // gap a file fits is the lowest of the heaps for its size and up.
+    pub struct Allocator {
+        heaps: Vec<BinaryHeap<Reverse<usize>>>,
+    }
+
+    impl Allocator {
+        pub fn new(spans: impl Iterator<Item = Span>) -> Self {
+            let mut heaps: Vec<BinaryHeap<Reverse<usize>>> = Vec::new();
+            for span in spans {
+                if heaps.len() <= span.size {
+                    heaps.resize_with(span.size + 1, BinaryHeap::new);
+                }
+                heaps[span.size].push(Reverse(span.pos));
+            }
+            Self { heaps }
+        }
+    }
+
+    impl super::Allocator for Allocator {
+        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span> {
+            let found = (*size..self.heaps.len())
+                .filter_map(|free| self.heaps[free].peek().map(|&Reverse(pos)| (pos, free)))
+                .min();
+            match found {
+                Some((pos, free)) if pos < max_pos => {
+                    self.heaps[free].pop();
+                    if free > *size {
+                        self.heaps[free - *size].push(Reverse(pos + *size));
+                    }
+                    let span = Span {
+                        pos,
+                        size: *size,
+                        block: Block::Free,
+                    };
+                    *size = 0;
+                    vec![span]
+                }
+                _ => vec![],
+            }
+        }
+    }
+}
+
+mod fit {
+    use crate::disk::Span;
+
+    // Picks one of the gaps a file fits, which are in disk order.
+    pub type Choose = fn(&[(usize, &Span)]) -> Option<usize>;
+
+    pub fn first(fits: &[(usize, &Span)]) -> Option<usize> {
+        fits.first().map(|&(i, _)| i)
+    }
+
+    pub fn best(fits: &[(usize, &Span)]) -> Option<usize> {
+        fits.iter()
+            .min_by_key(|(_, span)| span.size)
+            .map(|&(i, _)| i)
+    }
+
+    pub fn worst(fits: &[(usize, &Span)]) -> Option<usize> {
+        (fits.iter())
+            .min_by_key(|(_, span)| std::cmp::Reverse(span.size))
+            .map(|&(i, _)| i)
+    }
+
+    pub struct Allocator {
+        spans: Vec<Span>,
+        choose: Choose,
+    }
+
+    impl Allocator {
+        pub fn new(spans: impl Iterator<Item = Span>, choose: Choose) -> Self {
+            Self {
+                spans: spans.collect(),
+                choose,
+            }
+        }
+    }
+
+    impl super::Allocator for Allocator {
+        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span> {
+            let fits: Vec<(usize, &Span)> = (self.spans.iter().enumerate())
+                .take_while(|(_, s)| s.pos < max_pos)
+                .filter(|(_, s)| s.size >= *size)
+                .collect();
+            match (self.choose)(&fits) {
+                Some(i) => vec![super::take(&mut self.spans[i], size)],
+                None => vec![],
+            }
+        }
+    }
+}
+
+mod next_fit {
+    use crate::disk::Span;
+
+    pub struct Allocator {
+        spans: Vec<Span>,
+        // Where the last file went.
+        next: usize,
+    }
+
+    impl Allocator {
+        pub fn new(spans: impl Iterator<Item = Span>) -> Self {
+            Self {
+                spans: spans.collect(),
+                next: 0,
+            }
+        }
+    }
+
+    impl super::Allocator for Allocator {
+        fn alloc(&mut self, size: &mut usize, max_pos: usize) -> Vec<Span> {
+            let n = self.spans.len();
+            let found = (self.next..n).chain(0..self.next).find(|&i| {
+                let span = &self.spans[i];
+                span.pos < max_pos && span.size >= *size
+            });
+            match found {
+                Some(i) => {
+                    self.next = i;
+                    vec![super::take(&mut self.spans[i], size)]
+                }
+                None => vec![],
+            }
+        }
+    }
+}
+
+// Take a whole file's worth of blocks from the start of a free span.
+fn take(free: &mut Span, size: &mut usize) -> Span {
+    let mut span = free.clone();
+    span.size = *size;
+    free.pos += span.size;
+    free.size -= span.size;
+    *size = 0;
+    span
+}
+
+// The end of the disk that files are moved towards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, FromStr)]
+#[display(style = "kebab-case")]
+pub enum End {
+    Start,
+    End,
+}
+
+// A file that moved, perhaps in pieces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: Span,
+    pub to: Vec<Span>,
+}
+
+// Files moved one at a time, highest id first when compacting towards the
+// start. Towards the end the disk is mirrored, so the lowest id goes first.
+pub struct Compaction {
+    // The disk as the allocator sees it, mirrored towards the end.
+    files: Vec<Span>,
+    len: usize,
+    allocator: Box<dyn Allocator>,
+    towards: End,
+    // Indexes into `files` that are still to move.
+    pending: Vec<usize>,
+}
+
+impl Compaction {
+    pub fn new(disk: &Disk, strategy: Strategy, towards: End) -> Self {
+        let disk = match towards {
+            End::Start => disk.clone(),
+            End::End => disk.mirror(),
+        };
+        let files: Vec<Span> = disk.files_iter().cloned().collect();
+        Self {
+            len: disk.len(),
+            allocator: strategy.allocator(&disk),
+            towards,
+            pending: (0..files.len()).rev().collect(),
+            files,
+        }
+    }
+
+    fn unmirror(&self, span: &Span) -> Span {
+        match self.towards {
+            End::Start => span.clone(),
+            End::End => Span {
+                pos: self.len - span.end(),
+                ..span.clone()
+            },
+        }
+    }
+
+    // The disk as it is now.
+    pub fn disk(&self) -> Disk {
+        let disk = Disk::layout(self.files.iter().cloned(), self.len);
+        match self.towards {
+            End::Start => disk,
+            End::End => disk.mirror(),
+        }
+    }
+
+    // Move all the remaining files.
+    pub fn finish(mut self) -> Disk {
+        self.by_ref().for_each(drop);
+        self.disk()
+    }
+}
+
+impl Iterator for Compaction {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        while let Some(i) = self.pending.pop() {
+            let from = self.files[i].clone();
+            let mut size = from.size;
+            let mut to = self.allocator.alloc(&mut size, from.pos);
+            if to.is_empty() {
+                continue;
+            }
+            for span in &mut to {
+                span.block = from.block;
+            }
+            // What doesn't fit stays at the start of the file.
+            self.files[i].size = size;
+            self.files.extend(to.iter().cloned());
+            let to = to.iter().map(|span| self.unmirror(span)).collect();
+            return Some(Move {
+                from: self.unmirror(&from),
+                to,
+            });
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Block::File(id) = self.from.block {
+            write!(f, "file {id} ")?;
+        }
+        write!(f, "{}..{} ->", self.from.pos, self.from.end())?;
+        for span in &self.to {
+            write!(f, " {}..{}", span.pos, span.end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2333133121414131402";
+
+    fn compact(input: &str, strategy: Strategy, towards: End) -> Disk {
+        let disk: Disk = input.parse().unwrap();
+        Compaction::new(&disk, strategy, towards).finish()
+    }
+
+    fn steps(input: &str, strategy: Strategy) -> Vec<String> {
+        let disk: Disk = input.parse().unwrap();
+        let mut compaction = Compaction::new(&disk, strategy, End::Start);
+        let mut rendered = vec![];
+        while compaction.next().is_some() {
+            rendered.push(compaction.disk().to_string());
+        }
+        rendered
+    }
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(steps("12345", Strategy::Fragment), ["022111222......"]);
+        assert_eq!(
+            steps(EXAMPLE, Strategy::FirstFit),
+            [
+                "0099.111...2...333.44.5555.6666.777.8888..",
+                "0099.1117772...333.44.5555.6666.....8888..",
+                "0099.111777244.333....5555.6666.....8888..",
+                "00992111777.44.333....5555.6666.....8888..",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_towards_end() {
+        assert_eq!(
+            compact("12345", Strategy::Fragment, End::End).to_string(),
+            "......111022222"
+        );
+        let disk: Disk = "12345".parse().unwrap();
+        let mut compaction = Compaction::new(&disk, Strategy::Fragment, End::End);
+        let first = compaction.next().unwrap();
+        assert_eq!(first.to_string(), "file 0 0..1 -> 9..10");
+    }
+
+    #[test]
+    fn test_strategies() {
+        assert_eq!(
+            compact(EXAMPLE, Strategy::Fragment, End::Start).checksum(),
+            1928
+        );
+        assert_eq!(
+            compact(EXAMPLE, Strategy::FirstFit, End::Start).checksum(),
+            2858
+        );
+        let blocks = |disk: &Disk| disk.files_iter().map(|span| span.size).sum::<usize>();
+        for strategy in Strategy::ALL {
+            for towards in [End::Start, End::End] {
+                let disk = compact(EXAMPLE, strategy, towards);
+                assert_eq!(disk.len(), 42);
+                assert_eq!(blocks(&disk), 28);
+            }
+        }
+        let fragmentation = compact(EXAMPLE, Strategy::Fragment, End::Start).fragmentation();
+        assert_eq!((fragmentation.holes, fragmentation.split_files), (0, 2));
+        assert_eq!(
+            compact(EXAMPLE, Strategy::BestFit, End::Start).to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!("next-fit".parse(), Ok(Strategy::NextFit));
+    }
+
+    #[test]
+    fn test_first_fit_index() {
+        for seed in 0..20 {
+            let disk: Disk = crate::synthetic::disk_map(500, seed).parse().unwrap();
+            let indexed = Compaction::new(&disk, Strategy::FirstFit, End::Start).finish();
+            let scanned = Compaction::new(&disk, Strategy::FirstFitScan, End::Start).finish();
+            assert_eq!(indexed, scanned);
+            let mirror = |strategy| Compaction::new(&disk, strategy, End::End).finish();
+            assert_eq!(mirror(Strategy::FirstFit), mirror(Strategy::FirstFitScan));
+        }
+    }
+}
@@ -0,0 +1,29 @@
+// Random disk maps shaped like the puzzle's: files of 1 to 9 blocks with
+// gaps of 0 to 9 between them.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn digit(&mut self, low: u64) -> char {
+        char::from(b'0' + (low + self.next() % (10 - low)) as u8)
+    }
+}
+
+pub fn disk_map(files: usize, seed: u64) -> String {
+    let mut random = Random(seed);
+    let mut map = String::with_capacity(2 * files);
+    for i in 0..files {
+        if i > 0 {
+            map.push(random.digit(0));
+        }
+        map.push(random.digit(1));
+    }
+    map
+}
@@ -1,3 +1,4 @@
+use memo::Memo;
 use point::Point;
 struct Map {
     data: std::collections::HashMap<Point, char>,
@@ -31,43 +32,20 @@ trait Attribute: std::iter::Sum + Clone + std::fmt::Debug {
     fn unit(p: Point) -> Self;
 }
 
-struct Attributes<'a, T: Attribute> {
-    map: &'a Map,
-    data: std::collections::HashMap<Point, T>,
-}
-
-impl<'a, T: Attribute> Attributes<'a, T> {
-    fn new(map: &'a Map) -> Self {
-        Self {
-            map,
-            data: std::collections::HashMap::<Point, T>::new(),
-        }
-    }
-
-    fn calculate_attribute(&mut self, p: Point) -> T {
-        let n = self.map.data[&p].to_digit(10).unwrap();
-        if n == 9 {
-            return T::unit(p);
-        }
-        p.neighbors()
-            .filter_map(|other| {
-                if self.map.data.get(&other)?.to_digit(10).unwrap() == n + 1 {
-                    Some(self.get_attribute(other))
-                } else {
-                    None
-                }
-            })
-            .sum()
-    }
-
-    fn get_attribute(&mut self, p: Point) -> T {
-        if let Some(attribute) = self.data.get(&p) {
-            return attribute.clone();
-        }
-        let score = self.calculate_attribute(p);
-        self.data.insert(p, score.clone());
-        score
-    }
+fn attribute<T: Attribute>(memo: &mut Memo<Point, T>, map: &Map, p: Point) -> T {
+    let n = map.data[&p].to_digit(10).unwrap();
+    if n == 9 {
+        return T::unit(p);
+    }
+    p.neighbors()
+        .filter_map(|other| {
+            if map.data.get(&other)?.to_digit(10).unwrap() == n + 1 {
+                Some(memo.get(map, other, attribute))
+            } else {
+                None
+            }
+        })
+        .sum()
 }
 
 trait Part {
@@ -76,9 +54,9 @@ trait Part {
 
     fn run(input: &str) -> Self::Output {
         let map = input.parse::<Map>().expect("Parse failed.");
-        let mut attributes = Attributes::<Self::Attribute>::new(&map);
+        let mut memo = Memo::<Point, Self::Attribute>::new();
         map.trailheads_iter()
-            .map(|p| attributes.get_attribute(p).into())
+            .map(|p| memo.get(&map, p, attribute).into())
             .sum()
     }
 }
@@ -1,10 +1,8 @@
-use std::collections::HashSet;
-
 use derive_more::From;
 use direction::{CardinalDirection, CardinalDirectionIter};
-use disjoint_hash_set::DisjointHashSet;
 use grid::Grid;
 use itertools::Itertools;
+use union_find::{Aggregate, UnionFind};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, From)]
 struct PlantType(char);
@@ -17,6 +15,14 @@ struct Region {
     sides: usize,
 }
 
+impl Aggregate for Region {
+    fn merge(&mut self, other: Self) {
+        self.area += other.area;
+        self.perimeter += other.perimeter;
+        self.sides += other.sides;
+    }
+}
+
 fn edges_iter(
     rows: (usize, usize, usize),
     cols: (usize, usize, usize),
@@ -65,43 +71,40 @@ impl Map {
             .filter(|&(a, b)| self.same_plant_type(a, b))
     }
 
-    fn make_region(&self, plots: HashSet<(usize, usize)>) -> Region {
-        let plant_type = self.plant_type(*plots.iter().nth(0).unwrap());
-        let disjoint_walls: DisjointHashSet<((usize, usize), CardinalDirection)> = plots
+    // The statistics of a region consisting of just `plot`, counting each
+    // side by the wall at its left end.
+    fn plot_region(&self, plot: (usize, usize)) -> Region {
+        let walls: Vec<CardinalDirection> = CardinalDirectionIter::new()
+            .filter(|&dir| self.connected_neighbor(plot, dir).is_none())
+            .collect();
+        let sides = walls
             .iter()
-            .flat_map(|&plot| {
-                CardinalDirectionIter::new()
-                    .filter(move |&dir| self.connected_neighbor(plot, dir).is_none())
-                    .map(
-                        move |dir| match self.connected_neighbor(plot, dir.right90()) {
-                            Some(other) if self.connected_neighbor(other, dir).is_none() => {
-                                ((plot, dir), (other, dir))
-                            }
-                            _ => ((plot, dir), (plot, dir)),
-                        },
-                    )
+            .filter(|&&dir| {
+                !matches!(
+                    self.connected_neighbor(plot, dir.left90()),
+                    Some(other) if self.connected_neighbor(other, dir).is_none()
+                )
             })
-            .collect();
-        let mut perimeter = 0;
-        let mut sides = 0;
-        for segment in disjoint_walls.sets() {
-            sides += 1;
-            perimeter += segment.len();
-        }
+            .count();
         Region {
-            _plant_type: plant_type,
-            area: plots.len(),
-            perimeter,
+            _plant_type: self.plant_type(plot),
+            area: 1,
+            perimeter: walls.len(),
             sides,
         }
     }
 
     fn regions(&self) -> impl Iterator<Item = Region> {
-        let mut disjoint_plots: DisjointHashSet<(usize, usize)> = self.connected_plots().collect();
-        for plot in (0..self.0.rows()).cartesian_product(0..self.0.cols()) {
-            disjoint_plots.insert(plot);
+        let cols = self.0.cols();
+        let index = |(row, col): (usize, usize)| row * cols + col;
+        let mut regions: UnionFind<Region> = (0..self.0.rows())
+            .cartesian_product(0..cols)
+            .map(|plot| self.plot_region(plot))
+            .collect();
+        for (a, b) in self.connected_plots() {
+            regions.union(index(a), index(b));
         }
-        disjoint_plots.sets().map(|plots| self.make_region(plots))
+        regions.into_aggregates()
     }
 }
 
@@ -1,87 +1,356 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use std::collections::HashMap;
 use syn::spanned::Spanned;
 
 #[derive(deluxe::ExtractAttributes)]
 #[deluxe(attributes(bytecode))]
-struct ByteCode {
-    opcode: u8,
+struct Container {
+    // The type of a word of code.
+    #[deluxe(default)]
+    word: Option<syn::Type>,
+    // Pads every instruction out to this many words.
+    #[deluxe(default)]
+    width: Option<usize>,
 }
 
-fn bytecode_derive_macro2(
-    item: proc_macro2::TokenStream,
-) -> deluxe::Result<proc_macro2::TokenStream> {
+#[derive(deluxe::ExtractAttributes)]
+#[deluxe(attributes(bytecode))]
+struct Variant {
+    opcode: u64,
+    #[deluxe(default)]
+    mnemonic: Option<String>,
+}
+
+#[derive(deluxe::ExtractAttributes)]
+#[deluxe(attributes(bytecode))]
+struct Field {
+    // Packs the operand into this many bits of a word shared with its
+    // neighbours.
+    #[deluxe(default)]
+    bits: Option<u32>,
+}
+
+// Where an operand lives: a word of the instruction, and for packed
+// operands, a run of bits within it.
+struct Slot {
+    word: usize,
+    bits: Option<(u32, u32)>,
+}
+
+fn word_bits(word: &syn::Type) -> Option<u32> {
+    let syn::Type::Path(path) = word else {
+        return None;
+    };
+    match path.path.get_ident()?.to_string().as_str() {
+        "u8" | "i8" => Some(8),
+        "u16" | "i16" => Some(16),
+        "u32" | "i32" => Some(32),
+        "u64" | "i64" | "usize" | "isize" => Some(64),
+        "u128" | "i128" => Some(128),
+        _ => None,
+    }
+}
+
+// Lays out the fields after the opcode word, packing runs of bit fields into
+// shared words. Returns the slots and the number of words used.
+fn layout(word: &syn::Type, fields: &mut syn::Fields) -> deluxe::Result<(Vec<Slot>, usize)> {
+    let mut slots = Vec::new();
+    let mut words = 1;
+    let mut packed = None;
+    for field in fields.iter_mut() {
+        let Field { bits } = deluxe::extract_attributes(field)?;
+        let Some(bits) = bits else {
+            packed = None;
+            slots.push(Slot {
+                word: words,
+                bits: None,
+            });
+            words += 1;
+            continue;
+        };
+        let Some(available) = word_bits(word).filter(|&n| (1..=n).contains(&bits)) else {
+            return Err(syn::Error::new_spanned(
+                &*field,
+                format!("can't pack {bits} bits into a word of {}", quote!(#word)),
+            ));
+        };
+        let shift = match packed {
+            Some(used) if used + bits <= available => used,
+            _ => {
+                words += 1;
+                0
+            }
+        };
+        packed = Some(shift + bits);
+        // A field taking up a whole word needs no masking.
+        let bits = (bits < available).then_some((shift, bits));
+        slots.push(Slot {
+            word: words - 1,
+            bits,
+        });
+    }
+    Ok((slots, words))
+}
+
+fn bytecode_derive_macro2(item: TokenStream) -> deluxe::Result<TokenStream> {
     // parse
     let mut input = syn::parse2::<syn::DeriveInput>(item)?;
+    let Container { word, width } = deluxe::extract_attributes(&mut input)?;
+    let word = word.unwrap_or_else(|| syn::parse_quote!(u8));
+    let syn::Data::Enum(e) = &mut input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ByteCode can only be derived for enums",
+        ));
+    };
 
-    // extract field attributes
-    let mut new_matchers = Vec::new();
+    // extract variant and field attributes
+    let mut opcodes = Vec::new();
+    let mut decode_matchers = Vec::new();
+    let mut encode_matchers = Vec::new();
     let mut opcode_matchers = Vec::new();
-    let mut operand_matchers = Vec::new();
-    if let syn::Data::Enum(e) = &mut input.data {
-        for variant in e.variants.iter_mut() {
-            if variant.fields.len() > 1 {
-                panic!("too many fields in bytecode variant {}", variant.ident);
+    let mut seen = HashMap::new();
+    for variant in e.variants.iter_mut() {
+        let Variant { opcode, mnemonic } = deluxe::extract_attributes(variant)?;
+        let ident = &variant.ident;
+        if let Some(other) = seen.insert(opcode, ident.clone()) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("opcode {opcode} is already used by {other}"),
+            ));
+        }
+        let mnemonic = mnemonic.unwrap_or_else(|| ident.to_string().to_lowercase());
+        let (slots, mut words) = layout(&word, &mut variant.fields)?;
+        if let Some(width) = width {
+            if words > width {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!("{ident} needs {words} words but the width is {width}"),
+                ));
             }
-            let ByteCode { opcode } = deluxe::extract_attributes(variant)?;
-            let span = variant.span();
-            let ident = &variant.ident;
-            let members = variant.fields.members();
-            new_matchers.push(quote::quote_spanned! {span=>
-                #opcode => Self::#ident { #( #members: operand.into() ),* }
-            });
-            let members = variant.fields.members();
-            opcode_matchers.push(quote::quote_spanned! {span=>
-                Self::#ident { #( #members: _ ),* } => #opcode
-            });
-            let members = variant.fields.members();
-            if variant.fields.len() == 1 {
-                operand_matchers.push(quote::quote_spanned! {span=>
-                    Self::#ident { #( #members: operand ),* } => operand.into()
+            words = width;
+        }
+        let opcode = Literal::u64_unsuffixed(opcode);
+
+        // the opcode table entry
+        let operands =
+            variant
+                .fields
+                .iter()
+                .zip(&slots)
+                .enumerate()
+                .map(|(index, (field, slot))| {
+                    let name = match &field.ident {
+                        Some(name) => name.to_string(),
+                        None => index.to_string(),
+                    };
+                    let ty = &field.ty;
+                    let kind = quote!(#ty).to_string();
+                    let bits = match slot.bits {
+                        Some((_, bits)) => quote!(Some(#bits)),
+                        None => quote!(None),
+                    };
+                    quote! {
+                        bytecode::Operand { name: #name, kind: #kind, bits: #bits }
+                    }
                 });
+        opcodes.push(quote! {
+            bytecode::Opcode {
+                opcode: #opcode,
+                mnemonic: #mnemonic,
+                width: #words,
+                operands: &[ #( #operands ),* ],
             }
-        }
+        });
+
+        // decoding
+        let members: Vec<syn::Member> = variant.fields.members().collect();
+        let decoded = variant.fields.iter().zip(&slots).map(|(field, slot)| {
+            let span = field.ty.span();
+            let index = slot.word;
+            let operand = match slot.bits {
+                Some((shift, bits)) => {
+                    let mask = Literal::u128_unsuffixed((1 << bits) - 1);
+                    quote!((words[#index] >> #shift) & #mask)
+                }
+                None => quote!(words[#index]),
+            };
+            let converted = quote_spanned! {span=>
+                ::core::convert::TryFrom::try_from(operand)
+            };
+            quote! {{
+                let operand = #operand;
+                #converted.map_err(|_| bytecode::DecodeError::Operand { opcode, operand })?
+            }}
+        });
+        decode_matchers.push(quote! {
+            #opcode => {
+                let Some(words) = words.get(..#words) else {
+                    return Err(bytecode::DecodeError::Truncated { opcode });
+                };
+                (Self::#ident { #( #members: #decoded ),* }, #words)
+            }
+        });
+
+        // encoding
+        let bindings: Vec<syn::Ident> = (0..slots.len())
+            .map(|index| format_ident!("field{index}"))
+            .collect();
+        let encoded = (1..words).map(|index| {
+            let parts = variant
+                .fields
+                .iter()
+                .zip(&slots)
+                .zip(&bindings)
+                .filter(|((_, slot), _)| slot.word == index)
+                .map(|((field, slot), binding)| {
+                    let span = field.ty.span();
+                    let converted = quote_spanned! {span=>
+                        ::core::convert::From::from(::core::clone::Clone::clone(#binding))
+                    };
+                    let value = quote!({
+                        let value: #word = #converted;
+                        value
+                    });
+                    match slot.bits {
+                        Some((shift, bits)) => {
+                            let mask = Literal::u128_unsuffixed((1 << bits) - 1);
+                            quote!(((#value & #mask) << #shift))
+                        }
+                        None => value,
+                    }
+                })
+                .collect::<Vec<_>>();
+            if parts.is_empty() {
+                quote!(0)
+            } else {
+                quote!(#( #parts )|*)
+            }
+        });
+        encode_matchers.push(quote! {
+            Self::#ident { #( #members: #bindings ),* } => vec![#opcode, #( #encoded ),*]
+        });
+        let span = variant.span();
+        opcode_matchers.push(quote_spanned! {span=>
+            Self::#ident { .. } => #opcode
+        });
     }
 
     // define impl variables
     let ident = &input.ident;
     let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let fixed_width = match width {
+        Some(width) => quote!(Some(#width)),
+        None => quote!(None),
+    };
 
-    // generate
-    Ok(quote::quote! {
-        impl #impl_generics bytecode::ByteCode for #ident #type_generics #where_clause {
-            fn new(opcode: u8, operand: u8) -> Self {
-                match opcode {
-                    #( #new_matchers ),*,
-                    _ => panic!("invalid opcode"),
-                }
-            }
-            fn opcode(&self) -> u8 {
-                match *self {
-                    #( #opcode_matchers ),*,
+    // two-word encodings also convert to and from pairs
+    let pairs = (width == Some(2)).then(|| {
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics TryFrom<(#word, #word)> for #ident #type_generics #where_clause {
+                type Error = bytecode::DecodeError<#word>;
+
+                fn try_from((opcode, operand): (#word, #word)) -> Result<Self, Self::Error> {
+                    <Self as bytecode::ByteCode<#word>>::decode(&[opcode, operand])
+                        .map(|(this, _)| this)
                 }
             }
-            fn operand(&self) -> u8 {
-                match *self {
-                    #( #operand_matchers ),*,
-                    _ => 0,
+
+            #[automatically_derived]
+            impl #impl_generics From<#ident #type_generics> for (#word, #word) #where_clause {
+                fn from(this: #ident #type_generics) -> (#word, #word) {
+                    let mut words = bytecode::ByteCode::<#word>::encode(&this).into_iter();
+                    (words.next().unwrap(), words.next().unwrap())
                 }
             }
         }
+    });
 
-        impl #impl_generics From<(u8, u8)> for #ident #type_generics #where_clause {
-            fn from((opcode, operand): (u8, u8)) -> Self {
-                return Self::new(opcode, operand)
-            }
-        }
+    // generate; operands of the word type convert to themselves
+    Ok(quote! {
+        #[automatically_derived]
+        #[allow(clippy::useless_conversion)]
+        impl #impl_generics bytecode::ByteCode<#word> for #ident #type_generics #where_clause {
+            const OPCODES: &'static [bytecode::Opcode<#word>] = &[ #( #opcodes ),* ];
+            const WIDTH: Option<usize> = #fixed_width;
 
-        impl #impl_generics From<#ident> for (u8, u8) #type_generics #where_clause {
-            fn from(this: #ident) -> (u8, u8) {
-                return (this.opcode(), this.operand())
+            fn decode(words: &[#word]) -> Result<(Self, usize), bytecode::DecodeError<#word>> {
+                let Some(&opcode) = words.first() else {
+                    return Err(bytecode::DecodeError::Empty);
+                };
+                Ok(match opcode {
+                    #( #decode_matchers ),*
+                    _ => return Err(bytecode::DecodeError::Opcode(opcode)),
+                })
+            }
+            fn encode(&self) -> Vec<#word> {
+                match self {
+                    #( #encode_matchers ),*
+                }
+            }
+            fn opcode(&self) -> #word {
+                match self {
+                    #( #opcode_matchers ),*
+                }
             }
         }
+
+        #pairs
     })
 }
 
 #[proc_macro_derive(ByteCode, attributes(bytecode))]
 pub fn bytecode_derive_macro(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    bytecode_derive_macro2(item.into()).unwrap().into()
+    bytecode_derive_macro2(item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(item: TokenStream) -> String {
+        bytecode_derive_macro2(item).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error(quote! { struct S; }),
+            "ByteCode can only be derived for enums"
+        );
+        assert_eq!(
+            error(quote! {
+                enum E {
+                    #[bytecode(opcode = 0)]
+                    A,
+                    #[bytecode(opcode = 0)]
+                    B,
+                }
+            }),
+            "opcode 0 is already used by A"
+        );
+        assert_eq!(
+            error(quote! {
+                #[bytecode(width = 2)]
+                enum E {
+                    #[bytecode(opcode = 0)]
+                    A(u8, u8),
+                }
+            }),
+            "A needs 3 words but the width is 2"
+        );
+        assert_eq!(
+            error(quote! {
+                enum E {
+                    #[bytecode(opcode = 0)]
+                    A(#[bytecode(bits = 9)] u8),
+                }
+            }),
+            "can't pack 9 bits into a word of u8"
+        );
+    }
 }
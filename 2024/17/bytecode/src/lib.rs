@@ -1,5 +1,215 @@
-pub trait ByteCode {
-    fn new(opcode: u8, operand: u8) -> Self;
-    fn opcode(&self) -> u8;
-    fn operand(&self) -> u8;
+use std::fmt;
+
+// Lets the derive's `bytecode::` paths resolve in this crate's own tests.
+extern crate self as bytecode;
+
+// An instruction set encoded as a sequence of words: an opcode word followed
+// by operand words, possibly padded out to a fixed width.
+pub trait ByteCode<W: 'static = u8>: Sized {
+    const OPCODES: &'static [Opcode<W>];
+    // The width in words of every instruction, if fixed.
+    const WIDTH: Option<usize>;
+
+    // Decodes the instruction at the start of `words`, returning it and its
+    // width.
+    fn decode(words: &[W]) -> Result<(Self, usize), DecodeError<W>>;
+    fn encode(&self) -> Vec<W>;
+    fn opcode(&self) -> W;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Opcode<W> {
+    pub opcode: W,
+    pub mnemonic: &'static str,
+    pub width: usize,
+    pub operands: &'static [Operand],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Operand {
+    // The field name, or its index in a tuple variant.
+    pub name: &'static str,
+    // The field type.
+    pub kind: &'static str,
+    // The width of an operand packed into a word with others.
+    pub bits: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError<W = u8> {
+    Empty,
+    Opcode(W),
+    Operand { opcode: W, operand: W },
+    Truncated { opcode: W },
+}
+
+impl<W: fmt::Display> fmt::Display for DecodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Empty => write!(f, "no instruction"),
+            DecodeError::Opcode(opcode) => write!(f, "invalid opcode {opcode}"),
+            DecodeError::Operand { opcode, operand } => {
+                write!(f, "invalid operand {operand} for opcode {opcode}")
+            }
+            DecodeError::Truncated { opcode } => write!(f, "truncated opcode {opcode}"),
+        }
+    }
+}
+
+impl<W: fmt::Debug + fmt::Display> std::error::Error for DecodeError<W> {}
+
+pub fn opcode<T: ByteCode<W>, W: PartialEq + 'static>(opcode: &W) -> Option<&'static Opcode<W>> {
+    T::OPCODES.iter().find(|entry| entry.opcode == *opcode)
+}
+
+// Decodes instructions one after another, with their addresses and words.
+// Words that don't decode take up the width of their opcode if it's known,
+// or else the fixed width, or else a single word.
+pub fn decode_all<T: ByteCode<W>, W: PartialEq + 'static>(
+    words: &[W],
+) -> impl Iterator<Item = (usize, &[W], Result<T, DecodeError<W>>)> {
+    let mut address = 0;
+    std::iter::from_fn(move || {
+        let rest = words.get(address..).filter(|rest| !rest.is_empty())?;
+        let (decoded, width) = match T::decode(rest) {
+            Ok((instruction, width)) => (Ok(instruction), width),
+            Err(err) => {
+                let width = opcode::<T, W>(&rest[0])
+                    .map(|entry| entry.width)
+                    .or(T::WIDTH)
+                    .unwrap_or(1);
+                (Err(err), width.min(rest.len()))
+            }
+        };
+        let start = address;
+        address += width;
+        Some((start, &rest[..width], decoded))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytecode_derive::ByteCode;
+
+    // Intcode-like: variable length, word-sized operands.
+    #[derive(ByteCode, Debug, PartialEq)]
+    #[bytecode(word = i64)]
+    enum Intcode {
+        #[bytecode(opcode = 1)]
+        Add(i64, i64, i64),
+        #[bytecode(opcode = 3, mnemonic = "in")]
+        Input(i64),
+        #[bytecode(opcode = 99)]
+        Halt,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Register {
+        A,
+        B,
+    }
+
+    impl TryFrom<u8> for Register {
+        type Error = ();
+
+        fn try_from(value: u8) -> Result<Self, Self::Error> {
+            match value {
+                0 => Ok(Register::A),
+                1 => Ok(Register::B),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl From<Register> for u8 {
+        fn from(value: Register) -> Self {
+            value as u8
+        }
+    }
+
+    // Assembunny-like: operands packed into bytes.
+    #[derive(ByteCode, Debug, PartialEq)]
+    enum Bunny {
+        #[bytecode(opcode = 0)]
+        Cpy {
+            #[bytecode(bits = 4)]
+            value: u8,
+            #[bytecode(bits = 4)]
+            to: Register,
+        },
+        #[bytecode(opcode = 1)]
+        Inc(Register),
+        #[bytecode(opcode = 2)]
+        Jnz(#[bytecode(bits = 1)] Register, #[bytecode(bits = 7)] u8, u8),
+    }
+
+    #[test]
+    fn test_variable_length() {
+        let code = [1, 9, 10, 11, 3, 5, 99, 42];
+        let decoded: Vec<_> = decode_all::<Intcode, i64>(&code).collect();
+        assert_eq!(
+            decoded,
+            [
+                (0, &code[0..4], Ok(Intcode::Add(9, 10, 11))),
+                (4, &code[4..6], Ok(Intcode::Input(5))),
+                (6, &code[6..7], Ok(Intcode::Halt)),
+                (7, &code[7..8], Err(DecodeError::Opcode(42))),
+            ]
+        );
+        assert_eq!(Intcode::Add(9, 10, 11).encode(), &code[0..4]);
+        assert_eq!(
+            Intcode::decode(&[1, 2]),
+            Err(DecodeError::Truncated { opcode: 1 })
+        );
+        assert_eq!(Intcode::decode(&[]), Err(DecodeError::Empty));
+        assert_eq!(Intcode::WIDTH, None);
+    }
+
+    #[test]
+    fn test_packed() {
+        let cpy = Bunny::Cpy {
+            value: 9,
+            to: Register::B,
+        };
+        assert_eq!(cpy.encode(), [0, 0x19]);
+        assert_eq!(Bunny::decode(&[0, 0x19]), Ok((cpy, 2)));
+        let jnz = Bunny::Jnz(Register::B, 100, 7);
+        assert_eq!(jnz.encode(), [2, 201, 7]);
+        assert_eq!(Bunny::decode(&[2, 201, 7]), Ok((jnz, 3)));
+        assert_eq!(
+            Bunny::decode(&[0, 0x29]),
+            Err(DecodeError::Operand {
+                opcode: 0,
+                operand: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_opcodes() {
+        assert_eq!(
+            Intcode::OPCODES
+                .iter()
+                .map(|entry| (entry.opcode, entry.mnemonic, entry.width))
+                .collect::<Vec<_>>(),
+            [(1, "add", 4), (3, "in", 2), (99, "halt", 1)]
+        );
+        assert_eq!(
+            opcode::<Bunny, u8>(&0).unwrap().operands,
+            [
+                Operand {
+                    name: "value",
+                    kind: "u8",
+                    bits: Some(4)
+                },
+                Operand {
+                    name: "to",
+                    kind: "Register",
+                    bits: Some(4)
+                },
+            ]
+        );
+        assert_eq!(opcode::<Bunny, u8>(&1).unwrap().operands[0].name, "0");
+    }
 }
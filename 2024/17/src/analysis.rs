@@ -0,0 +1,219 @@
+use crate::instruction::{Combo, Instruction};
+use crate::machine::fetch;
+use graph::{DiGraph, NodeId};
+use parse_display::Display;
+
+type Program = [u8];
+
+#[derive(Debug, Display, PartialEq)]
+pub enum Unsupported {
+    #[display("no loop")]
+    NoLoop,
+    #[display("{0}: jnz other than the loop's")]
+    ExtraJump(usize),
+    #[display("{0}: adv by a register")]
+    VariableShift(usize),
+    #[display("{0}: loop doesn't shift A")]
+    NoProgress(usize),
+}
+
+// Control flow between the reachable instructions, by instruction pointer.
+// Instruction pointers with no instruction are where the program halts, or
+// faults on an instruction that doesn't decode.
+pub struct Cfg<'a> {
+    program: &'a Program,
+    graph: DiGraph<usize>,
+}
+
+impl<'a> Cfg<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut graph = DiGraph::new();
+        graph.add_node(0);
+        let mut pending = vec![0];
+        while let Some(instruction_pointer) = pending.pop() {
+            let Ok(Some(instruction)) = fetch(program, instruction_pointer) else {
+                continue;
+            };
+            let mut successors = vec![instruction_pointer + 2];
+            if let Instruction::Jnz(target) = instruction {
+                successors.push(target.into());
+            }
+            for successor in successors {
+                if graph.id(&successor).is_none() {
+                    pending.push(successor);
+                }
+                graph.add_edge(instruction_pointer, successor);
+            }
+        }
+        Self { program, graph }
+    }
+
+    // The reachable instructions, in address order.
+    pub fn instructions(&self) -> Vec<(usize, Instruction)> {
+        let names = self.graph.nodes().names();
+        let mut instructions: Vec<(usize, Instruction)> = names
+            .iter()
+            .filter_map(|&ip| Some((ip, fetch(self.program, ip).ok()??)))
+            .collect();
+        instructions.sort_by_key(|&(ip, _)| ip);
+        instructions
+    }
+
+    fn is_loop(&self, component: &[NodeId]) -> bool {
+        component.len() > 1 || self.graph.successors(component[0]).contains(&component[0])
+    }
+
+    // The instruction pointers in each loop, in address order.
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        let components = self.graph.strongly_connected_components();
+        components
+            .into_iter()
+            .filter(|component| self.is_loop(component))
+            .map(|component| {
+                let mut ips: Vec<usize> =
+                    component.iter().map(|&id| *self.graph.name(id)).collect();
+                ips.sort();
+                ips
+            })
+            .collect()
+    }
+
+    // Sums up the straight-line run from `start` through `end` inclusive.
+    fn summarize(&self, start: usize, end: usize) -> Summary {
+        let mut summary = Summary::default();
+        for instruction in (start..=end)
+            .step_by(2)
+            .map_while(|ip| fetch(self.program, ip).ok().flatten())
+        {
+            match instruction {
+                Instruction::Adv(Combo::Literal(n)) => summary.bits += u32::from(n),
+                Instruction::Out(_) => summary.outputs += 1,
+                _ => (),
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq)]
+#[display("{bits} bits of A, {outputs} outputs")]
+pub struct Summary {
+    pub bits: u32,
+    pub outputs: usize,
+}
+
+// A program that runs straight into a single loop, which shifts A by a
+// constant each time around and exits through a `jnz` once A is zero, and
+// then runs straight to the end.
+#[derive(Debug, Display, PartialEq)]
+#[display("first pass: {first}\nper iteration: {iteration}\nafter the loop: {after}")]
+pub struct Shape {
+    // From the start through the first `jnz`.
+    pub first: Summary,
+    // Once more around the loop, back to the `jnz`.
+    pub iteration: Summary,
+    // From the `jnz` to the end.
+    pub after: Summary,
+}
+
+impl Shape {
+    // The number of bits of A consumed before the final check of A, and
+    // in total, when the program produces `output_len` outputs.
+    pub fn bits_of_a_needed(&self, output_len: usize) -> Option<(u8, u8)> {
+        let repeated = output_len.checked_sub(self.first.outputs + self.after.outputs)?;
+        let iterations = match self.iteration.outputs {
+            0 => (repeated == 0).then_some(0)?,
+            n => (repeated % n == 0).then_some(repeated / n)?,
+        };
+        let iterations = u32::try_from(iterations).ok()?;
+        let max_bits = self.first.bits + iterations * self.iteration.bits;
+        let min_bits = match iterations {
+            0 => 0,
+            _ => max_bits - self.iteration.bits,
+        };
+        (max_bits < 64).then_some((min_bits as u8, max_bits as u8))
+    }
+}
+
+pub fn analyze(program: &Program) -> Result<Shape, Unsupported> {
+    use Instruction::*;
+    let cfg = Cfg::new(program);
+    let instructions = cfg.instructions();
+    let mut jumps = Vec::new();
+    for &(ip, instruction) in &instructions {
+        match instruction {
+            Adv(Combo::Literal(_)) => (),
+            Adv(_) => return Err(Unsupported::VariableShift(ip)),
+            Jnz(target) => jumps.push((ip, usize::from(target))),
+            _ => (),
+        }
+    }
+    let (exit, target) = match jumps[..] {
+        [] => return Err(Unsupported::NoLoop),
+        [jump] => jump,
+        [_, (ip, _), ..] => return Err(Unsupported::ExtraJump(ip)),
+    };
+    // With a single jump the only possible loop runs back to it.
+    if cfg.loops().is_empty() {
+        return Err(Unsupported::NoLoop);
+    }
+    let shape = Shape {
+        first: cfg.summarize(0, exit),
+        iteration: cfg.summarize(target, exit),
+        after: cfg.summarize(exit + 2, program.len()),
+    };
+    if shape.iteration.bits == 0 {
+        return Err(Unsupported::NoProgress(exit));
+    }
+    Ok(shape)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_loops() {
+        let program = assemble("bxl 1\nl: adv 3\nout a\njnz l\nout b").unwrap();
+        assert_eq!(Cfg::new(&program).loops(), [vec![2, 4, 6]]);
+        let shape = analyze(&program).unwrap();
+        assert_eq!(
+            shape.first,
+            Summary {
+                bits: 3,
+                outputs: 1
+            }
+        );
+        assert_eq!(
+            shape.iteration,
+            Summary {
+                bits: 3,
+                outputs: 1
+            }
+        );
+        assert_eq!(
+            shape.after,
+            Summary {
+                bits: 0,
+                outputs: 1
+            }
+        );
+        assert_eq!(shape.bits_of_a_needed(1), None);
+        assert_eq!(shape.bits_of_a_needed(2), Some((0, 3)));
+        assert_eq!(shape.bits_of_a_needed(4), Some((6, 9)));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let analyze = |source| analyze(&assemble(source).unwrap());
+        assert_eq!(analyze("out a"), Err(Unsupported::NoLoop));
+        assert_eq!(analyze("jnz 4\nout a"), Err(Unsupported::NoLoop));
+        assert_eq!(analyze("adv b\njnz 0"), Err(Unsupported::VariableShift(0)));
+        assert_eq!(
+            analyze("out a\njnz 0\njnz 0"),
+            Err(Unsupported::ExtraJump(4))
+        );
+        assert_eq!(analyze("out a\njnz 0"), Err(Unsupported::NoProgress(2)));
+    }
+}
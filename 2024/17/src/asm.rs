@@ -0,0 +1,242 @@
+use crate::instruction::{Combo, Instruction};
+use bytecode::{ByteCode, DecodeError};
+use parse_display::Display;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/*
+Assembly syntax, one instruction per line:
+
+    ; comments start with `;` or `#`
+    loop:           labels name the address of the next instruction
+        bst a       combo operands are 0-3 or a register name
+        bxl 1
+        out b
+        jnz loop    jump targets are a label or an address
+    7,7             lines of numbers are copied through as raw bytes
+*/
+
+#[derive(Debug, Display, PartialEq)]
+pub enum AssembleError {
+    #[display("line {0}: invalid instruction `{1}`")]
+    InvalidInstruction(usize, String),
+    #[display("line {0}: invalid label `{1}`")]
+    InvalidLabel(usize, String),
+    #[display("line {0}: duplicate label `{1}`")]
+    DuplicateLabel(usize, String),
+    #[display("line {0}: undefined label `{1}`")]
+    UndefinedLabel(usize, String),
+    #[display("line {0}: label `{1}` is out of reach of jnz")]
+    JumpOutOfRange(usize, String),
+}
+
+// An assembled line, possibly with a jump target still to be resolved.
+enum Item<'a> {
+    Bytes(Vec<u8>),
+    Jump(usize, &'a str),
+}
+
+impl Item<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Item::Bytes(bytes) => bytes.len(),
+            Item::Jump(..) => 2,
+        }
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    s.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+        && s.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+fn parse_operand(s: &str) -> Option<u8> {
+    s.parse().ok().filter(|&n| n < 8)
+}
+
+fn parse_line(number: usize, line: &str) -> Result<Item<'_>, AssembleError> {
+    let invalid = || AssembleError::InvalidInstruction(number, line.into());
+    if line.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return line
+            .split(',')
+            .map(|byte| byte.trim().parse().ok())
+            .collect::<Option<_>>()
+            .map(Item::Bytes)
+            .ok_or_else(invalid);
+    }
+    let (mnemonic, operand) = match line.split_once(char::is_whitespace) {
+        Some((mnemonic, operand)) => (mnemonic, Some(operand.trim())),
+        None => (line, None),
+    };
+    let instruction: Instruction = match (mnemonic, operand) {
+        ("jnz", Some(target)) if is_label(target) => return Ok(Item::Jump(number, target)),
+        ("bxc", Some(operand)) => {
+            // bxc ignores its operand, but keep it so programs round-trip.
+            let operand = parse_operand(operand).ok_or_else(invalid)?;
+            return Ok(Item::Bytes(vec![Instruction::Bxc.opcode(), operand]));
+        }
+        (mnemonic, Some(operand)) => format!("{mnemonic} {operand}").parse(),
+        (mnemonic, None) => mnemonic.parse(),
+    }
+    .map_err(|_| invalid())?;
+    let bytes = instruction.encode();
+    let valid = match instruction.combo() {
+        Some(Combo::Literal(n)) => n < 4,
+        Some(_) => true,
+        None => bytes[1] < 8,
+    };
+    if !valid {
+        return Err(invalid());
+    }
+    Ok(Item::Bytes(bytes))
+}
+
+pub fn assemble(source: &str) -> Result<Box<[u8]>, AssembleError> {
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut items: Vec<Item> = Vec::new();
+    let mut address = 0;
+    for (number, line) in (1..).zip(source.lines()) {
+        let mut line = line.split([';', '#']).next().unwrap().trim();
+        while let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(AssembleError::InvalidLabel(number, label.into()));
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(AssembleError::DuplicateLabel(number, label.into()));
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let item = parse_line(number, line)?;
+        address += item.len();
+        items.push(item);
+    }
+    let mut program = Vec::with_capacity(address);
+    for item in items {
+        match item {
+            Item::Bytes(bytes) => program.extend(bytes),
+            Item::Jump(number, label) => {
+                let &target = labels
+                    .get(label)
+                    .ok_or_else(|| AssembleError::UndefinedLabel(number, label.into()))?;
+                let target = u8::try_from(target)
+                    .ok()
+                    .filter(|&target| target < 8)
+                    .ok_or_else(|| AssembleError::JumpOutOfRange(number, label.into()))?;
+                program.extend([Instruction::Jnz(target).opcode(), target]);
+            }
+        }
+    }
+    Ok(program.into())
+}
+
+pub fn disassemble(program: &[u8]) -> String {
+    let decoded: Vec<(usize, &[u8], Result<Instruction, DecodeError>)> =
+        bytecode::decode_all(program).collect();
+    let targets: BTreeSet<u8> = decoded
+        .iter()
+        .filter_map(|(_, _, instruction)| match instruction {
+            Ok(Instruction::Jnz(target)) => Some(*target),
+            _ => None,
+        })
+        .filter(|&target| {
+            decoded
+                .iter()
+                .any(|&(address, ..)| address == target.into())
+        })
+        .collect();
+    let mut text = String::new();
+    for (address, bytes, instruction) in decoded {
+        if u8::try_from(address).is_ok_and(|address| targets.contains(&address)) {
+            writeln!(text, "l{address}:").unwrap();
+        }
+        match (instruction, bytes) {
+            (Ok(Instruction::Jnz(target)), _) if targets.contains(&target) => {
+                writeln!(text, "    jnz l{target}")
+            }
+            (Ok(Instruction::Bxc), &[_, operand]) if operand != 0 => {
+                writeln!(text, "    bxc {operand}")
+            }
+            (Ok(instruction), _) => writeln!(text, "    {instruction}"),
+            (Err(DecodeError::Operand { opcode, operand }), _) => {
+                // Name the instruction, as the operand is all that's wrong.
+                let mnemonic = bytecode::opcode::<Instruction, u8>(&opcode)
+                    .unwrap()
+                    .mnemonic;
+                writeln!(text, "    {opcode},{operand} ; {mnemonic} {operand}?")
+            }
+            (Err(_), bytes) => {
+                let bytes: Vec<String> = bytes.iter().map(u8::to_string).collect();
+                writeln!(text, "    {}", bytes.join(","))
+            }
+        }
+        .unwrap();
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = include_str!("../data/input");
+
+    #[test]
+    fn test_assemble() {
+        assert_eq!(assemble("adv 0").unwrap(), [0u8, 0u8].into());
+        assert_eq!(
+            assemble("start: bst a ; B = A % 8\n  out b\njnz start").unwrap(),
+            [2, 4, 5, 5, 3, 0].into()
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        use AssembleError::*;
+        assert_eq!(
+            assemble("adv 4"),
+            Err(InvalidInstruction(1, "adv 4".into()))
+        );
+        assert_eq!(
+            assemble("bxl 8"),
+            Err(InvalidInstruction(1, "bxl 8".into()))
+        );
+        assert_eq!(assemble("x:\nx:"), Err(DuplicateLabel(2, "x".into())));
+        assert_eq!(
+            assemble("jnz nowhere"),
+            Err(UndefinedLabel(1, "nowhere".into()))
+        );
+        assert_eq!(
+            assemble("bxc\nbxc\nbxc\nbxc\nfar: jnz far"),
+            Err(JumpOutOfRange(5, "far".into()))
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(
+            disassemble(&[0, 3, 5, 4, 3, 0]),
+            "l0:\n    adv 3\n    out a\n    jnz l0\n"
+        );
+        assert_eq!(
+            disassemble(&[4, 5, 3, 1, 7, 7, 6]),
+            "    bxc 5\n    jnz 1\n    7,7 ; cdv 7?\n    6\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = INPUT.rsplit_once(' ').unwrap().1;
+        assert_eq!(
+            assemble(&disassemble(&assemble(program).unwrap())).unwrap(),
+            assemble(program).unwrap()
+        );
+        assert_eq!(
+            assemble(&disassemble(&[4, 5, 3, 1, 7, 7, 6])).unwrap(),
+            [4, 5, 3, 1, 7, 7, 6].into()
+        );
+    }
+}
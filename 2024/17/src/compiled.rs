@@ -0,0 +1,211 @@
+use crate::instruction::{Combo, Instruction};
+use crate::machine::{Register, Registers, fetch, run_program, shr};
+use bytecode::DecodeError;
+use std::ops::Range;
+use std::time::Instant;
+
+type Program = [u8];
+
+// A program decoded once up front, for running many times. Jumps may land
+// on odd addresses, so there's a decoded instruction for every address.
+pub struct Compiled {
+    instructions: Box<[Result<Option<Instruction>, DecodeError>]>,
+}
+
+impl Compiled {
+    pub fn new(program: &Program) -> Self {
+        Self {
+            instructions: (0..program.len())
+                .map(|instruction_pointer| fetch(program, instruction_pointer))
+                .collect(),
+        }
+    }
+
+    // Runs until the program halts or `out` returns false, or fails on an
+    // instruction that doesn't decode.
+    pub fn run(
+        &self,
+        registers: &mut Registers,
+        mut out: impl FnMut(u8) -> bool,
+    ) -> Result<(), DecodeError> {
+        let Registers { a, b, c } = registers;
+        let combo = |combo: Combo, a: Register, b: Register, c: Register| match combo {
+            Combo::Literal(lit) => lit.into(),
+            Combo::A => a,
+            Combo::B => b,
+            Combo::C => c,
+        };
+        let mut instruction_pointer = 0;
+        while let Some(&instruction) = self.instructions.get(instruction_pointer) {
+            use Instruction::*;
+            let Some(instruction) = instruction? else {
+                break;
+            };
+            instruction_pointer += 2;
+            match instruction {
+                Adv(value) => *a = shr(*a, combo(value, *a, *b, *c)),
+                Bxl(value) => *b ^= Register::from(value),
+                Bst(value) => *b = combo(value, *a, *b, *c) % 8,
+                Jnz(target) => {
+                    if *a != 0 {
+                        instruction_pointer = target.into();
+                    }
+                }
+                Bxc => *b ^= *c,
+                Out(value) => {
+                    if !out((combo(value, *a, *b, *c) % 8) as u8) {
+                        break;
+                    }
+                }
+                Bdv(value) => *b = shr(*a, combo(value, *a, *b, *c)),
+                Cdv(value) => *c = shr(*a, combo(value, *a, *b, *c)),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn output(&self, mut registers: Registers) -> Result<Vec<u8>, DecodeError> {
+        let mut output = Vec::new();
+        self.run(&mut registers, |out| {
+            output.push(out);
+            true
+        })?;
+        Ok(output)
+    }
+
+    // The output for each candidate value of A, with B and C taken from
+    // `registers`.
+    pub fn outputs<'a>(
+        &'a self,
+        registers: &'a Registers,
+        candidates: impl IntoIterator<Item = Register> + 'a,
+    ) -> impl Iterator<Item = (Register, Result<Vec<u8>, DecodeError>)> + 'a {
+        candidates.into_iter().map(|a| {
+            let registers = Registers {
+                a,
+                ..registers.clone()
+            };
+            (a, self.output(registers))
+        })
+    }
+
+    // The candidates for which the program outputs exactly `target` and
+    // doesn't fault.
+    pub fn matching<'a>(
+        &'a self,
+        registers: &'a Registers,
+        candidates: impl IntoIterator<Item = Register> + 'a,
+        target: &'a [u8],
+    ) -> impl Iterator<Item = Register> + 'a {
+        candidates.into_iter().filter(move |&a| {
+            let mut registers = Registers {
+                a,
+                ..registers.clone()
+            };
+            let mut rest = target;
+            let mut matched = true;
+            let ran = self.run(&mut registers, |out| {
+                matched = rest.first() == Some(&out);
+                rest = rest.get(1..).unwrap_or_default();
+                matched
+            });
+            ran.is_ok() && matched && rest.is_empty()
+        })
+    }
+}
+
+// Times the compiled program against `run_program` over the candidates,
+// both collecting every output and searching for a quine.
+pub fn bench(program: &Program, registers: &Registers, candidates: Range<Register>) -> String {
+    let compiled = Compiled::new(program);
+    let with_a = |a| Registers {
+        a,
+        ..registers.clone()
+    };
+    let report = |name: &str, interpreted: &dyn Fn() -> usize, compiled: &dyn Fn() -> usize| {
+        let start = Instant::now();
+        let expected = interpreted();
+        let interpreted = start.elapsed();
+        let start = Instant::now();
+        assert_eq!(compiled(), expected);
+        let compiled = start.elapsed();
+        let speedup = interpreted.as_secs_f64() / compiled.as_secs_f64();
+        format!("{name}: {interpreted:?} interpreted, {compiled:?} compiled ({speedup:.1}x)")
+    };
+    let outputs = report(
+        "outputs",
+        &|| {
+            candidates
+                .clone()
+                .map(|a| {
+                    let mut registers = with_a(a);
+                    let mut machine = run_program(&mut registers, program);
+                    let count = machine.by_ref().count();
+                    machine.fault().map_or(count, |_| 0)
+                })
+                .sum()
+        },
+        &|| {
+            compiled
+                .outputs(registers, candidates.clone())
+                .map(|(_, output)| output.map_or(0, |output| output.len()))
+                .sum()
+        },
+    );
+    let quines = report(
+        "quines",
+        &|| {
+            candidates
+                .clone()
+                .filter(|&a| {
+                    let mut registers = with_a(a);
+                    let mut machine = run_program(&mut registers, program);
+                    machine.by_ref().eq(program.iter().copied()) && machine.fault().is_none()
+                })
+                .count()
+        },
+        &|| {
+            compiled
+                .matching(registers, candidates.clone(), program)
+                .count()
+        },
+    );
+    format!("{outputs}\n{quines}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::Puzzle;
+
+    const INPUT: &'static str = include_str!("../data/input");
+
+    #[test]
+    fn test_run_program() {
+        let puzzle: Puzzle = INPUT.parse().unwrap();
+        let compiled = Compiled::new(&puzzle.program);
+        let registers = Registers {
+            a: 0,
+            ..puzzle.registers
+        };
+        for (a, output) in compiled.outputs(&registers, (0..1000).chain([u64::MAX])) {
+            let mut registers = Registers {
+                a,
+                ..registers.clone()
+            };
+            let expected: Vec<u8> = run_program(&mut registers, &puzzle.program).collect();
+            assert_eq!(output, Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_matching() {
+        let program = [0, 3, 5, 4, 3, 0];
+        let compiled = Compiled::new(&program);
+        let registers = Registers { a: 0, b: 0, c: 0 };
+        let found: Vec<u64> = compiled
+            .matching(&registers, 117430..117450, &program)
+            .collect();
+        assert_eq!(found, Vec::from_iter(117440..117448));
+    }
+}
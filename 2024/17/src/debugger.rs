@@ -0,0 +1,282 @@
+use crate::asm::disassemble;
+use crate::instruction::Instruction;
+use crate::machine::{self, Registers, Step};
+use bytecode::DecodeError;
+use parse_display::{Display, FromStr};
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+
+type Program = [u8];
+
+#[derive(Clone, Copy, Debug, Display, FromStr, PartialEq)]
+pub enum Breakpoint {
+    #[display("{0}")]
+    Address(usize),
+    #[display("{0}")]
+    Instruction(Instruction),
+}
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum Stop {
+    #[display("halted")]
+    Halted,
+    #[display("fault: {0}")]
+    Fault(DecodeError),
+    #[display("breakpoint {0}")]
+    Breakpoint(Breakpoint),
+    #[display("step limit reached")]
+    StepLimit,
+}
+
+// Runs a program one instruction at a time, recording every step.
+pub struct Debugger<'a> {
+    machine: machine::Iter<'a>,
+    breakpoints: Vec<Breakpoint>,
+    step_limit: usize,
+    pub trace: Vec<Step>,
+    pub output: Vec<u8>,
+}
+
+impl<'a> Debugger<'a> {
+    pub const DEFAULT_STEP_LIMIT: usize = 1_000_000;
+
+    pub fn new(registers: &'a mut Registers, program: &'a Program) -> Self {
+        Self {
+            machine: machine::run_program(registers, program),
+            breakpoints: Vec::new(),
+            step_limit: Self::DEFAULT_STEP_LIMIT,
+            trace: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn set_step_limit(&mut self, step_limit: usize) {
+        self.step_limit = step_limit;
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn registers(&self) -> &Registers {
+        self.machine.registers()
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.machine.instruction_pointer()
+    }
+
+    fn breakpoint(&self) -> Option<Breakpoint> {
+        let instruction = self.machine.peek()?;
+        let instruction_pointer = self.machine.instruction_pointer();
+        self.breakpoints
+            .iter()
+            .copied()
+            .find(|&breakpoint| match breakpoint {
+                Breakpoint::Address(address) => address == instruction_pointer,
+                Breakpoint::Instruction(i) => i == instruction,
+            })
+    }
+
+    pub fn step(&mut self) -> Result<&Step, Stop> {
+        if self.trace.len() >= self.step_limit {
+            return Err(Stop::StepLimit);
+        }
+        let Some(step) = self.machine.step() else {
+            return Err(self.machine.fault().map_or(Stop::Halted, Stop::Fault));
+        };
+        self.output.extend(step.output);
+        self.trace.push(step);
+        Ok(self.trace.last().unwrap())
+    }
+
+    // Execute at least one instruction, stopping before the next instruction
+    // that matches a breakpoint.
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            if let Err(stop) = self.step() {
+                return stop;
+            }
+            if let Some(breakpoint) = self.breakpoint() {
+                return Stop::Breakpoint(breakpoint);
+            }
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Registers { a, b, c } = &self.before;
+        write!(
+            f,
+            "{:2}: {:<6} a={a} b={b} c={c}",
+            self.instruction_pointer,
+            self.instruction.to_string()
+        )?;
+        let Registers { a, b, c } = &self.after;
+        write!(f, " -> a={a} b={b} c={c}")?;
+        if let Some(output) = self.output {
+            write!(f, " out={output}")?;
+        }
+        Ok(())
+    }
+}
+
+const HELP: &str = "\
+step [n]     execute n instructions (default 1)
+continue     run until a breakpoint, halt or the step limit
+break <bp>   break before an address or an instruction, e.g. `break 6` or `break out b`
+limit <n>    stop after n instructions in total
+regs         show the registers and instruction pointer
+disasm       show the program
+output       show the output so far
+quit";
+
+// An interactive session reading commands from `input`.
+pub fn run(
+    debugger: &mut Debugger,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> std::io::Result<()> {
+    let program = debugger.machine.program();
+    for line in input.lines() {
+        let line = line?;
+        let (command, argument) = match line.trim().split_once(' ') {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line.trim(), ""),
+        };
+        match command {
+            "s" | "step" => {
+                for _ in 0..argument.parse().unwrap_or(1) {
+                    match debugger.step() {
+                        Ok(step) => writeln!(output, "{step}")?,
+                        Err(stop) => {
+                            writeln!(output, "{stop}")?;
+                            break;
+                        }
+                    }
+                }
+            }
+            "c" | "continue" => {
+                let start = debugger.trace.len();
+                let stop = debugger.resume();
+                for step in &debugger.trace[start..] {
+                    writeln!(output, "{step}")?;
+                }
+                writeln!(output, "{stop}")?;
+            }
+            "b" | "break" => match argument.parse() {
+                Ok(breakpoint) => debugger.add_breakpoint(breakpoint),
+                Err(_) => writeln!(output, "invalid breakpoint `{argument}`")?,
+            },
+            "l" | "limit" => match argument.parse() {
+                Ok(step_limit) => debugger.set_step_limit(step_limit),
+                Err(_) => writeln!(output, "invalid limit `{argument}`")?,
+            },
+            "r" | "regs" => {
+                let Registers { a, b, c } = debugger.registers();
+                let ip = debugger.instruction_pointer();
+                writeln!(output, "a={a} b={b} c={c} ip={ip}")?;
+            }
+            "d" | "disasm" => write!(output, "{}", disassemble(program))?,
+            "o" | "output" => {
+                let values: Vec<String> = debugger.output.iter().map(u8::to_string).collect();
+                writeln!(output, "{}", values.join(","))?;
+            }
+            "q" | "quit" => break,
+            "" => (),
+            _ => writeln!(output, "{HELP}")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instruction::Combo;
+
+    fn program() -> (Registers, Box<[u8]>) {
+        let registers = Registers {
+            a: 2024,
+            b: 0,
+            c: 0,
+        };
+        let program = crate::asm::assemble("loop: adv 1\nout a\njnz loop").unwrap();
+        (registers, program)
+    }
+
+    #[test]
+    fn test_trace() {
+        let (mut registers, program) = program();
+        let mut debugger = Debugger::new(&mut registers, &program);
+        assert_eq!(debugger.resume(), Stop::Halted);
+        assert_eq!(debugger.output, [4, 2, 5, 6, 7, 7, 7, 7, 3, 1, 0]);
+        assert_eq!(debugger.trace.len(), 33);
+        let step = &debugger.trace[1];
+        assert_eq!(step.instruction, Instruction::Out(Combo::A));
+        assert_eq!((step.before.a, step.output), (1012, Some(4)));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let (mut registers, program) = program();
+        let mut debugger = Debugger::new(&mut registers, &program);
+        debugger.add_breakpoint("out a".parse().unwrap());
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Instruction(Instruction::Out(Combo::A)))
+        );
+        assert_eq!(debugger.instruction_pointer(), 2);
+        debugger.add_breakpoint("0".parse().unwrap());
+        assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Address(0)));
+        assert_eq!(debugger.output, [4]);
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut registers = Registers { a: 1, b: 0, c: 0 };
+        let program = crate::asm::assemble("l: jnz l").unwrap();
+        let mut debugger = Debugger::new(&mut registers, &program);
+        debugger.set_step_limit(100);
+        assert_eq!(debugger.resume(), Stop::StepLimit);
+        assert_eq!(debugger.trace.len(), 100);
+    }
+
+    #[test]
+    fn test_fault() {
+        let mut registers = Registers { a: 1, b: 0, c: 0 };
+        let program = [5, 4, 0, 7];
+        let mut debugger = Debugger::new(&mut registers, &program);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Fault(DecodeError::Operand {
+                opcode: 0,
+                operand: 7
+            })
+        );
+        assert_eq!(debugger.output, [1]);
+    }
+
+    #[test]
+    fn test_session() {
+        let (mut registers, program) = program();
+        let mut debugger = Debugger::new(&mut registers, &program);
+        let mut output = Vec::new();
+        run(
+            &mut debugger,
+            "step 2\nregs\noutput\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                " 0: adv 1  a=2024 b=0 c=0 -> a=1012 b=0 c=0\n",
+                " 2: out a  a=1012 b=0 c=0 -> a=1012 b=0 c=0 out=4\n",
+                "a=1012 b=0 c=0 ip=4\n",
+                "4\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::asm::{assemble, disassemble};
+use crate::compiled::Compiled;
+use crate::machine::{Registers, run_program};
+use proptest::prelude::*;
+
+const STEP_LIMIT: usize = 1000;
+
+// How a program run by the reference interpreter stopped.
+struct Stopped {
+    output: Vec<u8>,
+    registers: Registers,
+    // Whether it stopped on the reserved combo operand 7.
+    faulted: bool,
+}
+
+// A direct reading of the puzzle statement that shares no code with the
+// machine. Returns `None` for programs that run past the step limit.
+fn reference(program: &[u8], registers: &Registers) -> Option<Stopped> {
+    let Registers {
+        mut a,
+        mut b,
+        mut c,
+    } = registers.clone();
+    let mut output = Vec::new();
+    let mut instruction_pointer = 0;
+    for _ in 0..STEP_LIMIT {
+        let (Some(&opcode), Some(&operand)) = (
+            program.get(instruction_pointer),
+            program.get(instruction_pointer + 1),
+        ) else {
+            return Some(Stopped {
+                output,
+                registers: Registers { a, b, c },
+                faulted: false,
+            });
+        };
+        let literal = u64::from(operand);
+        let combo = match operand {
+            0..=3 => Some(literal),
+            4 => Some(a),
+            5 => Some(b),
+            6 => Some(c),
+            _ => None,
+        };
+        // Combo operand 7 is reserved, and the machine faults on it.
+        let Some(combo) = combo.or(matches!(opcode, 1 | 3 | 4).then_some(0)) else {
+            return Some(Stopped {
+                output,
+                registers: Registers { a, b, c },
+                faulted: true,
+            });
+        };
+        let divide = |numerator: u64| match combo {
+            0..64 => numerator / (1 << combo),
+            _ => 0,
+        };
+        match opcode {
+            0 => a = divide(a),
+            1 => b ^= literal,
+            2 => b = combo % 8,
+            3 if a != 0 => {
+                instruction_pointer = operand.into();
+                continue;
+            }
+            3 => (),
+            4 => b ^= c,
+            5 => output.push((combo % 8) as u8),
+            6 => b = divide(a),
+            7 => c = divide(a),
+            _ => unreachable!("opcodes are three bits"),
+        }
+        instruction_pointer += 2;
+    }
+    None
+}
+
+fn program() -> impl Strategy<Value = Vec<u8>> {
+    // Operands are three bits, including the reserved combo operand 7.
+    let instruction = (0u8..8, 0u8..8);
+    prop::collection::vec(instruction, 0..12).prop_map(|instructions| {
+        instructions
+            .into_iter()
+            .flat_map(|(op, arg)| [op, arg])
+            .collect()
+    })
+}
+
+fn register() -> impl Strategy<Value = u64> {
+    prop_oneof![0u64..1 << 12, any::<u64>()]
+}
+
+fn registers() -> impl Strategy<Value = Registers> {
+    (register(), register(), register()).prop_map(|(a, b, c)| Registers { a, b, c })
+}
+
+proptest! {
+    #[test]
+    fn test_machines_agree(program in program(), registers in registers()) {
+        let Some(Stopped { output, registers: after, faulted }) = reference(&program, &registers) else {
+            // Too slow to check; the others would run as long.
+            return Ok(());
+        };
+
+        let mut machine = registers.clone();
+        let mut iter = run_program(&mut machine, &program);
+        let machine_output: Vec<u8> = iter.by_ref().collect();
+        let fault = iter.fault();
+        prop_assert_eq!(&machine_output, &output);
+        prop_assert_eq!(&machine, &after);
+        prop_assert_eq!(fault.is_some(), faulted);
+
+        let mut compiled = registers.clone();
+        let mut compiled_output = Vec::new();
+        let ran = Compiled::new(&program).run(&mut compiled, |out| {
+            compiled_output.push(out);
+            true
+        });
+        prop_assert_eq!(&compiled_output, &output);
+        prop_assert_eq!(&compiled, &after);
+        prop_assert_eq!(ran.err(), fault);
+    }
+
+    #[test]
+    fn test_round_trip(program in prop::collection::vec(0u8..8, 0..24)) {
+        let source = disassemble(&program);
+        let assembled = assemble(&source);
+        prop_assert_eq!(assembled.as_deref(), Ok(&program[..]), "{}", source);
+    }
+}
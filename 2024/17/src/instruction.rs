@@ -1,10 +1,10 @@
-use bytecode::ByteCode;
 use bytecode_derive::ByteCode;
 use parse_display::{Display, FromStr};
 
 #[derive(Clone, Copy, Display, FromStr, ByteCode, Debug, PartialEq)]
 #[display(style = "lowercase")]
 #[display("{} {0}")]
+#[bytecode(width = 2)]
 pub enum Instruction {
     #[bytecode(opcode = 0)]
     Adv(Combo),
@@ -25,6 +25,16 @@ pub enum Instruction {
     Cdv(Combo),
 }
 
+impl Instruction {
+    pub fn combo(&self) -> Option<Combo> {
+        use Instruction::*;
+        match *self {
+            Adv(combo) | Bst(combo) | Out(combo) | Bdv(combo) | Cdv(combo) => Some(combo),
+            Bxl(_) | Jnz(_) | Bxc => None,
+        }
+    }
+}
+
 pub trait Machine<T> {
     fn combo(&self, combo: Combo) -> T;
     fn adv(&mut self, value: T);
@@ -51,6 +61,7 @@ pub trait Machine<T> {
 }
 
 #[derive(Clone, Copy, Display, FromStr, Debug, PartialEq)]
+#[display(style = "lowercase")]
 #[display("{}")]
 pub enum Combo {
     #[display("{0}")]
@@ -60,15 +71,20 @@ pub enum Combo {
     C,
 }
 
-impl From<u8> for Combo {
-    fn from(value: u8) -> Self {
+#[derive(Debug, PartialEq)]
+pub struct InvalidCombo(pub u8);
+
+impl TryFrom<u8> for Combo {
+    type Error = InvalidCombo;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         use Combo::*;
         match value {
-            value @ 0..=3 => Literal(value),
-            4 => A,
-            5 => B,
-            6 => C,
-            _ => panic!("invalid operand"),
+            value @ 0..=3 => Ok(Literal(value)),
+            4 => Ok(A),
+            5 => Ok(B),
+            6 => Ok(C),
+            _ => Err(InvalidCombo(value)),
         }
     }
 }
@@ -85,29 +101,37 @@ impl From<Combo> for u8 {
     }
 }
 
-#[cfg(test)]
-pub fn assemble(instructions: &[Instruction]) -> Box<[u8]> {
-    instructions
-        .into_iter()
-        .flat_map(|inst| vec![inst.opcode(), inst.operand()].into_iter())
-        .collect()
-}
-
 #[cfg(test)]
 mod test {
     use super::{
         Combo::*,
         Instruction::{self, *},
-        assemble,
     };
 
     #[test]
-    fn test_1() {
-        assert_eq!(assemble(&[Adv(Literal(0))]), [0u8, 0u8].into());
+    fn test_2() {
+        assert_eq!("adv 0".parse::<Instruction>().unwrap(), Adv(Literal(0)));
     }
 
     #[test]
-    fn test_2() {
-        assert_eq!("adv 0".parse::<Instruction>().unwrap(), Adv(Literal(0)));
+    fn test_decode() {
+        use bytecode::DecodeError;
+        assert_eq!(Instruction::try_from((5, 5)), Ok(Out(B)));
+        assert_eq!(Instruction::try_from((1, 7)), Ok(Bxl(7)));
+        assert_eq!(Instruction::try_from((8, 0)), Err(DecodeError::Opcode(8)));
+        assert_eq!(
+            Instruction::try_from((0, 7)),
+            Err(DecodeError::Operand {
+                opcode: 0,
+                operand: 7
+            })
+        );
+        assert_eq!(super::Combo::try_from(7), Err(super::InvalidCombo(7)));
+    }
+
+    #[test]
+    fn test_3() {
+        assert_eq!("out b".parse::<Instruction>().unwrap(), Out(B));
+        assert_eq!(Cdv(C).to_string(), "cdv c");
     }
 }
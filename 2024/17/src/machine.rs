@@ -1,9 +1,13 @@
 use crate::instruction::{Combo, Instruction, Machine};
+use bytecode::DecodeError;
 use parse_display::{Display, FromStr};
+use std::borrow::BorrowMut;
+use std::collections::VecDeque;
+use vm::{Effect, Execute, Status, Vm};
 
-type Register = u64;
+pub type Register = u64;
 
-#[derive(Clone, Display, FromStr)]
+#[derive(Clone, Debug, Display, FromStr, PartialEq, Eq)]
 #[display("{a}\n{b}\n{c}")]
 pub struct Registers {
     #[display("Register A: {}")]
@@ -16,14 +20,26 @@ pub struct Registers {
 
 type Program = [u8];
 
+// Division by a power of two; large shifts give zero rather than overflow.
+pub fn shr(value: Register, shift: Register) -> Register {
+    u32::try_from(shift)
+        .ok()
+        .and_then(|shift| value.checked_shr(shift))
+        .unwrap_or(0)
+}
+
 pub struct Iter<'a> {
+    vm: Vm<&'a mut Registers, &'a Program>,
+}
+
+// The machine as seen by a single instruction.
+struct Cpu<'a> {
     registers: &'a mut Registers,
-    program: &'a Program,
-    instruction_pointer: usize,
-    pending_output: Option<u8>,
+    output: &'a mut VecDeque<u8>,
+    jump: Option<usize>,
 }
 
-impl<'a> Machine<Register> for Iter<'a> {
+impl<'a> Machine<Register> for Cpu<'a> {
     fn combo(&self, combo: Combo) -> Register {
         use Combo::*;
         match combo {
@@ -34,7 +50,7 @@ impl<'a> Machine<Register> for Iter<'a> {
         }
     }
     fn adv(&mut self, value: Register) {
-        self.registers.a = self.registers.a >> value;
+        self.registers.a = shr(self.registers.a, value);
     }
     fn bxl(&mut self, value: u8) {
         self.registers.b ^= Register::from(value);
@@ -44,61 +60,123 @@ impl<'a> Machine<Register> for Iter<'a> {
     }
     fn jnz(&mut self, value: u8) {
         if self.registers.a != 0 {
-            self.instruction_pointer = value.into();
+            self.jump = Some(value.into());
         }
     }
     fn bxc(&mut self) {
         self.registers.b ^= self.registers.c;
     }
     fn out(&mut self, value: Register) {
-        assert!(self.pending_output.is_none());
         let output: u8 = (value % 8).try_into().unwrap();
-        self.pending_output = Some(output);
+        self.output.push_back(output);
     }
     fn bdv(&mut self, value: Register) {
-        self.registers.b = self.registers.a >> value;
+        self.registers.b = shr(self.registers.a, value);
     }
     fn cdv(&mut self, value: Register) {
-        self.registers.c = self.registers.a >> value;
+        self.registers.c = shr(self.registers.a, value);
     }
 }
 
-pub fn run_program<'a>(
-    registers: &'a mut Registers,
-    program: &'a Program,
-) -> impl Iterator<Item = u8> {
+impl<R: BorrowMut<Registers>, M> Execute<R, M> for Instruction {
+    fn execute(&self, vm: &mut Vm<R, M>) -> Effect {
+        let mut cpu = Cpu {
+            registers: vm.registers.borrow_mut(),
+            output: &mut vm.output,
+            jump: None,
+        };
+        cpu.execute(*self);
+        match cpu.jump {
+            Some(target) => Effect::Jump(target),
+            None => Effect::Next,
+        }
+    }
+}
+
+pub fn run_program<'a>(registers: &'a mut Registers, program: &'a Program) -> Iter<'a> {
     Iter {
-        registers,
-        program,
-        instruction_pointer: 0usize,
-        pending_output: None,
+        vm: Vm::new(registers, program),
     }
 }
 
-pub fn fetch(program: &Program, instruction_pointer: usize) -> Option<Instruction> {
+// The instruction at `instruction_pointer`, or `None` if that's off the end
+// of the program and the machine halts. An instruction that doesn't decode
+// is an error, and the machine faults on it.
+pub fn fetch(
+    program: &Program,
+    instruction_pointer: usize,
+) -> Result<Option<Instruction>, DecodeError> {
     if program.len() < instruction_pointer + 2 {
-        return None;
+        return Ok(None);
     }
     let opcode = program[instruction_pointer];
     let operand = program[instruction_pointer + 1];
-    Some((opcode, operand).into())
+    (opcode, operand).try_into().map(Some)
+}
+
+// The effect of executing a single instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub instruction_pointer: usize,
+    pub instruction: Instruction,
+    pub before: Registers,
+    pub after: Registers,
+    pub output: Option<u8>,
 }
 
+impl<'a> Iter<'a> {
+    pub fn instruction_pointer(&self) -> usize {
+        self.vm.pc
+    }
+
+    pub fn registers(&self) -> &Registers {
+        self.vm.registers
+    }
+
+    pub fn program(&self) -> &'a Program {
+        self.vm.memory
+    }
+
+    // Why the program stopped, if it stopped on an instruction that doesn't
+    // decode rather than by running off the end.
+    pub fn fault(&self) -> Option<DecodeError> {
+        match self.vm.status() {
+            Status::Fault(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    // The instruction that will be executed next, if the program hasn't halted.
+    pub fn peek(&self) -> Option<Instruction> {
+        let (instruction, _) = self.vm.fetch().ok()?;
+        Some(instruction)
+    }
+
+    // Execute one instruction, or return `None` if the program has halted or
+    // faulted.
+    pub fn step(&mut self) -> Option<Step> {
+        let instruction_pointer = self.vm.pc;
+        let before = self.vm.registers.clone();
+        let instruction = self.vm.step().ok()?;
+        Some(Step {
+            instruction_pointer,
+            instruction,
+            before,
+            after: self.vm.registers.clone(),
+            output: self.vm.output.pop_front(),
+        })
+    }
+}
+
+// The output stops when the program halts or faults; `fault` tells which.
 impl<'a> Iterator for Iter<'a> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.pending_output.is_none() {
-            if let Some(instruction) = fetch(self.program, self.instruction_pointer) {
-                self.instruction_pointer += 2;
-                self.execute(instruction);
-            } else {
-                return None;
-            }
+        while self.vm.output.is_empty() {
+            self.vm.step::<Instruction>().ok()?;
         }
-        let out = self.pending_output;
-        self.pending_output = None;
-        return out;
+        self.vm.output.pop_front()
     }
 }
 
@@ -171,4 +249,41 @@ mod test {
         assert_eq!(output, []);
         assert_eq!(registers.b, 44354);
     }
+
+    #[test]
+    fn test_invalid() {
+        let mut registers = Registers { a: 5, b: 0, c: 0 };
+        let program = [
+            5, 4, // out A
+            0, 7, // adv 7 doesn't decode
+            5, 4, // out A
+        ];
+        let mut machine = run_program(&mut registers, &program);
+        assert_eq!(machine.by_ref().collect::<Vec<u8>>(), [5]);
+        assert_eq!(
+            machine.fault(),
+            Some(DecodeError::Operand {
+                opcode: 0,
+                operand: 7
+            })
+        );
+        assert_eq!(machine.instruction_pointer(), 2);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut registers = Registers { a: 10, b: 0, c: 0 };
+        let program = [
+            0, 1, // adv 1
+            5, 4, // out A
+        ];
+        let mut machine = run_program(&mut registers, &program);
+        let step = machine.step().unwrap();
+        assert_eq!(step.instruction_pointer, 0);
+        assert_eq!((step.before.a, step.after.a, step.output), (10, 5, None));
+        let step = machine.step().unwrap();
+        assert_eq!((step.instruction_pointer, step.output), (2, Some(5)));
+        assert_eq!(machine.step(), None);
+        assert_eq!(machine.fault(), None);
+    }
 }
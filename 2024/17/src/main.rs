@@ -1,11 +1,99 @@
+mod analysis;
+mod asm;
+mod compiled;
+mod debugger;
+#[cfg(test)]
+mod differential;
 mod instruction;
 mod machine;
-mod puzzle;
 mod part1;
 mod part2;
+mod puzzle;
+mod symbolic;
 
 fn main() {
     const INPUT: &'static str = include_str!("../data/input");
-    println!("Part 1: {}", part1::run(INPUT));
-    println!("Part 2: {}", part2::run(INPUT));
+    match std::env::args().nth(1).as_deref() {
+        // Print the input program as assembly.
+        Some("disasm") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("Parse failed.");
+            print!("{}", asm::disassemble(&puzzle.program));
+        }
+        // Describe the shape of the input program's loop.
+        Some("analyze") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("Parse failed.");
+            match analysis::analyze(&puzzle.program) {
+                Ok(shape) => println!("{shape}"),
+                Err(err) => println!("unsupported: {err}"),
+            }
+        }
+        // Compare the interpreter and the compiled program on part 2's search.
+        Some("bench") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("Parse failed.");
+            let registers = machine::Registers { a: 0, b: 0, c: 0 };
+            println!(
+                "{}",
+                compiled::bench(&puzzle.program, &registers, 0..1 << 20)
+            );
+        }
+        // Find the smallest values of A, 10 unless a count is given, for
+        // which the input program prints the given output.
+        Some("find") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("Parse failed.");
+            let target = std::env::args().nth(2).unwrap_or_default();
+            let target: Vec<u8> = target
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse().expect("Parse failed."))
+                .collect();
+            let count: usize = std::env::args()
+                .nth(3)
+                .map_or(10, |count| count.parse().expect("Parse failed."));
+            match part2::find_smallest_register_a(
+                &puzzle.program,
+                &target,
+                &puzzle.registers,
+                count,
+            ) {
+                Ok(found) if found.is_empty() => println!("{}", part2::Unsolved::NoSolution),
+                Ok(found) => {
+                    for a in found {
+                        println!("{a}");
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        // Assemble stdin and print it in the puzzle's format.
+        Some("asm") => {
+            let source = std::io::read_to_string(std::io::stdin()).expect("Read failed.");
+            match asm::assemble(&source) {
+                Ok(program) => {
+                    let bytes: Vec<String> = program.iter().map(u8::to_string).collect();
+                    println!("Program: {}", bytes.join(","));
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        // Debug the input program interactively.
+        Some("debug") => {
+            let mut puzzle: puzzle::Puzzle = INPUT.parse().expect("Parse failed.");
+            let mut debugger = debugger::Debugger::new(&mut puzzle.registers, &puzzle.program);
+            debugger::run(&mut debugger, std::io::stdin().lock(), std::io::stdout())
+                .expect("I/O failed.");
+        }
+        _ => {
+            println!("Part 1: {}", part1::run(INPUT));
+            match part2::run(INPUT) {
+                Ok(a) => println!("Part 2: {a}"),
+                Err(err) => println!("Part 2: {err}"),
+            }
+        }
+    }
 }
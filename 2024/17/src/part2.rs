@@ -1,78 +1,50 @@
-use crate::instruction::Combo;
-use crate::instruction::Instruction;
-use crate::machine;
-use crate::machine::fetch;
+use crate::analysis::{self, Shape};
+use crate::compiled::Compiled;
+use crate::machine::{self, Registers};
 use crate::puzzle::Puzzle;
+use crate::symbolic::{self, SearchIncomplete};
+use parse_display::Display;
 
 type Program = [u8];
 
-fn run_out(program: &Program, mut instruction_pointer: usize) -> usize {
-    let mut len = 0;
-    while let Some(instruction) = fetch(program, instruction_pointer) {
-        use Instruction::*;
-        if let Out(_) = instruction {
-            len += 1;
-        }
-        instruction_pointer += 2;
-    }
-    len
-}
-
-fn bits_of_a_needed(program: &Program, mut output_len: usize) -> (u8, u8) {
-    let mut min_bits = 0;
-    let mut bits = 0;
-    let mut instruction_pointer = 0;
-    loop {
-        use Combo::*;
-        use Instruction::*;
-        let instruction = fetch(program, instruction_pointer).expect("end of program");
-        instruction_pointer += 2;
-        match instruction {
-            Adv(Literal(n)) => bits += n,
-            Adv(_) => panic!("not implemented"),
-            Jnz(0) => {
-                // Try where a = 0 at this point.
-                if run_out(program, instruction_pointer + 2) == output_len {
-                    return (min_bits, bits);
-                }
-                // That didn't work so a != 0.
-                min_bits = bits;
-                instruction_pointer = 0;
-            }
-            Jnz(_) => panic!("not implemented"),
-            Out(_) => {
-                assert_ne!(output_len, 0, "too much output");
-                output_len -= 1;
-            }
-            _ => (),
-        };
-    }
-}
-
-fn try_one(program: &Program, mut output: &[u8], a: u64) -> usize {
+fn try_one(compiled: &Compiled, mut output: &[u8], a: u64) -> usize {
     let mut registers = machine::Registers { a, b: 0, c: 0 };
     let mut matches = 0usize;
-    for out in machine::run_program(&mut registers, program) {
-        if out == output[0] {
+    let mut too_long = false;
+    let ran = compiled.run(&mut registers, |out| {
+        let Some((&expected, rest)) = output.split_first() else {
+            too_long = true;
+            return false;
+        };
+        if out == expected {
             matches += 1;
         } else {
             matches = 0;
         }
-        output = &output[1..];
-    }
-    (output.is_empty()).then_some(matches).unwrap_or(0)
+        output = rest;
+        true
+    });
+    (ran.is_ok() && output.is_empty() && !too_long)
+        .then_some(matches)
+        .unwrap_or(0)
 }
 
-fn find_a(program: &Program, output: &[u8], mut a: u64, matches: usize) -> Option<u64> {
+fn find_a(
+    compiled: &Compiled,
+    shape: &Shape,
+    output: &[u8],
+    mut a: u64,
+    matches: usize,
+) -> Option<u64> {
     // precondition: try_one(program, output, starting_a) == matches
     if matches == output.len() {
         return Some(a);
     }
-    let (min_bits, max_bits) = bits_of_a_needed(program, output.len() - matches);
+    let (min_bits, max_bits) = shape.bits_of_a_needed(output.len() - matches)?;
     let max_a = a + (1u64 << u64::from(max_bits));
     while a < max_a {
-        if try_one(program, output, a) > matches {
-            if let Some(a) = find_a(program, output, a, matches + 1) {
+        if try_one(compiled, output, a) > matches {
+            if let Some(a) = find_a(compiled, shape, output, a, matches + 1) {
                 return Some(a);
             }
         }
@@ -81,19 +53,114 @@ fn find_a(program: &Program, output: &[u8], mut a: u64, matches: usize) -> Optio
     return None;
 }
 
-pub fn run(input: &str) -> u64 {
+#[derive(Debug, Display, PartialEq)]
+pub enum Unsolved {
+    #[display("no solution")]
+    NoSolution,
+    #[display("{0}")]
+    Incomplete(SearchIncomplete),
+}
+
+// The values of register A, smallest first, for which the program outputs
+// exactly `target` with B and C starting as in `registers`. There can be
+// far too many to list, so take only as many as needed. An error ends the
+// values if the search couldn't rule out any larger ones.
+pub fn find_register_a(
+    program: &Program,
+    target: &[u8],
+    registers: &Registers,
+) -> impl Iterator<Item = Result<u64, SearchIncomplete>> + use<> {
+    symbolic::find_all(program, target, registers.b, registers.c)
+}
+
+// Up to `n` values of register A, smallest first.
+pub fn find_smallest_register_a(
+    program: &Program,
+    target: &[u8],
+    registers: &Registers,
+    n: usize,
+) -> Result<Vec<u64>, SearchIncomplete> {
+    find_register_a(program, target, registers)
+        .take(n)
+        .collect()
+}
+
+pub fn run(input: &str) -> Result<u64, Unsolved> {
     let puzzle: Puzzle = input.parse().expect("Parse failed.");
-    find_a(&puzzle.program, &puzzle.program, 0, 0).expect("not found")
+    let program = &puzzle.program;
+    let registers = Registers { a: 0, b: 0, c: 0 };
+    match analysis::analyze(program) {
+        Ok(shape) => find_a(&Compiled::new(program), &shape, program, 0, 0),
+        Err(_) => find_register_a(program, program, &registers)
+            .next()
+            .transpose()
+            .map_err(Unsolved::Incomplete)?,
+    }
+    .ok_or(Unsolved::NoSolution)
 }
 
 #[cfg(test)]
 mod test {
-    use super::run;
+    use super::*;
 
     const EXAMPLE2: &'static str = include_str!("../data/example2");
+    const INPUT: &'static str = include_str!("../data/input");
 
     #[test]
     fn test_example2() {
-        assert_eq!(run(EXAMPLE2), 117440);
+        assert_eq!(run(EXAMPLE2), Ok(117440));
+    }
+
+    #[test]
+    fn test_find_register_a() {
+        let program = [0, 3, 5, 4, 3, 0];
+        let registers = Registers { a: 0, b: 0, c: 0 };
+        assert!(find_register_a(&program, &program, &registers).eq((117440..117448).map(Ok)));
+        assert_eq!(
+            find_smallest_register_a(&program, &[1, 0], &registers, 2),
+            Ok(vec![8, 9])
+        );
+        assert_eq!(find_register_a(&program, &[], &registers).next(), None);
+        // Output B, whatever A is, so every A is a solution.
+        let registers = Registers { a: 0, b: 5, c: 0 };
+        assert!(
+            find_register_a(&[5, 5], &[5], &registers)
+                .take(3)
+                .eq([Ok(0), Ok(1), Ok(2)])
+        );
+        assert_eq!(
+            find_register_a(&[5, 5], &[5], &registers).nth(19),
+            Some(Ok(19))
+        );
+        assert_eq!(find_register_a(&[5, 5], &[4], &registers).next(), None);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let input = "Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 5,0";
+        assert_eq!(run(input), Err(Unsolved::NoSolution));
+    }
+
+    #[test]
+    fn test_incomplete() {
+        // Loops forever unless A is zero, and prints nothing either way.
+        let input = "Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 3,0";
+        assert_eq!(
+            run(input),
+            Err(Unsolved::Incomplete(SearchIncomplete { from: 1 }))
+        );
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let puzzle: Puzzle = INPUT.parse().unwrap();
+        let program = &puzzle.program;
+        let shape = analysis::analyze(program).unwrap();
+        assert_eq!(
+            find_smallest_register_a(program, program, &puzzle.registers, 1)
+                .unwrap()
+                .first(),
+            find_a(&Compiled::new(program), &shape, program, 0, 0).as_ref()
+        );
     }
 }
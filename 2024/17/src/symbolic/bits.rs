@@ -0,0 +1,168 @@
+// Bit-blasting: bit-vectors are vectors of literals, least significant bit
+// first, and each gate is encoded into the solver as it is built. Constant
+// inputs fold away and identical gates are shared.
+
+use super::sat::{Lit, Solver};
+use std::collections::HashMap;
+
+pub type Bits = Vec<Lit>;
+
+pub fn constant(value: u64, width: usize) -> Bits {
+    (0..width)
+        .map(|i| match value.checked_shr(i as u32).unwrap_or(0) & 1 {
+            0 => Lit::FALSE,
+            _ => Lit::TRUE,
+        })
+        .collect()
+}
+
+pub struct Builder {
+    pub solver: Solver,
+    ands: HashMap<(Lit, Lit), Lit>,
+    xors: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            solver: Solver::new(),
+            ands: HashMap::new(),
+            xors: HashMap::new(),
+        }
+    }
+
+    pub fn input(&mut self, width: usize) -> Bits {
+        (0..width).map(|_| self.solver.new_var()).collect()
+    }
+
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        let (a, b) = (a.min(b), a.max(b));
+        if a == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if a == Lit::TRUE || a == b {
+            return b;
+        }
+        if let Some(&gate) = self.ands.get(&(a, b)) {
+            return gate;
+        }
+        let gate = self.solver.new_var();
+        self.solver.add_clause(&[!gate, a]);
+        self.solver.add_clause(&[!gate, b]);
+        self.solver.add_clause(&[gate, !a, !b]);
+        self.ands.insert((a, b), gate);
+        gate
+    }
+
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let (a, b) = (a.min(b), a.max(b));
+        match a {
+            Lit::FALSE => return b,
+            Lit::TRUE => return !b,
+            _ if a == b => return Lit::FALSE,
+            _ if a == !b => return Lit::TRUE,
+            _ => (),
+        }
+        if let Some(&gate) = self.xors.get(&(a, b)) {
+            return gate;
+        }
+        let gate = self.solver.new_var();
+        self.solver.add_clause(&[!gate, a, b]);
+        self.solver.add_clause(&[!gate, !a, !b]);
+        self.solver.add_clause(&[gate, !a, b]);
+        self.solver.add_clause(&[gate, a, !b]);
+        self.xors.insert((a, b), gate);
+        gate
+    }
+
+    // `then` if `select` is true, otherwise `otherwise`.
+    pub fn mux(&mut self, select: Lit, then: Lit, otherwise: Lit) -> Lit {
+        if then == otherwise {
+            return then;
+        }
+        let then = self.and(select, then);
+        let otherwise = self.and(!select, otherwise);
+        self.or(then, otherwise)
+    }
+
+    // True if any bit is set.
+    pub fn any(&mut self, bits: &[Lit]) -> Lit {
+        match bits {
+            [] => Lit::FALSE,
+            [bit] => *bit,
+            _ => {
+                let (low, high) = bits.split_at(bits.len() / 2);
+                let (low, high) = (self.any(low), self.any(high));
+                self.or(low, high)
+            }
+        }
+    }
+
+    pub fn xor_bits(&mut self, a: &[Lit], b: &[Lit]) -> Bits {
+        a.iter().zip(b).map(|(&a, &b)| self.xor(a, b)).collect()
+    }
+
+    // Logical shift right by a variable amount; shifting by the width or
+    // more gives zero.
+    pub fn shr(&mut self, value: &[Lit], amount: &[Lit]) -> Bits {
+        let width = value.len();
+        let stages = width.next_power_of_two().trailing_zeros() as usize;
+        let mut result = value.to_vec();
+        for (stage, &select) in amount.iter().enumerate().take(stages) {
+            let distance = 1 << stage;
+            result = (0..width)
+                .map(|i| {
+                    let shifted = result.get(i + distance).copied().unwrap_or(Lit::FALSE);
+                    self.mux(select, shifted, result[i])
+                })
+                .collect();
+        }
+        let overflow = amount.get(stages..).unwrap_or(&[]);
+        let overflow = self.any(overflow);
+        result.iter().map(|&bit| self.and(!overflow, bit)).collect()
+    }
+
+    pub fn value(&self, bits: &[Lit]) -> u64 {
+        bits.iter().rev().fold(0, |value, &bit| {
+            value << 1 | u64::from(self.solver.model_value(bit))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shr() {
+        let mut builder = Builder::new();
+        let value = builder.input(8);
+        let amount = builder.input(8);
+        let result = builder.shr(&value, &amount);
+        for (v, n) in [
+            (0b1011_0110, 0),
+            (0b1011_0110, 3),
+            (0xff, 7),
+            (0xff, 8),
+            (0xff, 200),
+        ] {
+            let mut assumptions = Vec::new();
+            for (bits, x) in [(&value, v), (&amount, n)] {
+                let expected = constant(x, 8);
+                assumptions.extend(
+                    bits.iter().zip(expected).map(
+                        |(&bit, e)| {
+                            if e == Lit::TRUE { bit } else { !bit }
+                        },
+                    ),
+                );
+            }
+            assert!(builder.solver.solve(&assumptions));
+            assert_eq!(builder.value(&result), v.checked_shr(n as u32).unwrap_or(0));
+        }
+    }
+}
@@ -0,0 +1,328 @@
+// Symbolic execution of the machine over bit-vectors. Register A starts out
+// as 64 unknown bits; every path through the program that halts with the
+// wanted output becomes a SAT query for the smallest satisfying A.
+
+mod bits;
+mod sat;
+
+use crate::instruction::{Combo, Machine};
+use crate::machine::fetch;
+use bits::{Bits, Builder, constant};
+use parse_display::Display;
+use sat::Lit;
+
+type Program = [u8];
+
+const WIDTH: usize = 64;
+
+// Paths running longer than this are abandoned, and the search reports
+// that it's incomplete rather than miss their values of A.
+const STEP_LIMIT: usize = 100_000;
+
+/// The search abandoned a path that ran too long, so it can't rule out
+/// values of A from `from` on.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[display("search incomplete from A = {from}")]
+pub struct SearchIncomplete {
+    pub from: u64,
+}
+
+#[derive(Clone)]
+struct Path {
+    a: Bits,
+    b: Bits,
+    c: Bits,
+    instruction_pointer: usize,
+    outputs: usize,
+    steps: usize,
+    // Everything that must hold for the program to take this path.
+    conditions: Vec<Lit>,
+}
+
+struct Executor<'a> {
+    builder: &'a mut Builder,
+    path: &'a mut Path,
+    jump: Option<u8>,
+    output: Option<Bits>,
+}
+
+impl<'a> Machine<Bits> for Executor<'a> {
+    fn combo(&self, combo: Combo) -> Bits {
+        use Combo::*;
+        match combo {
+            Literal(lit) => constant(lit.into(), WIDTH),
+            A => self.path.a.clone(),
+            B => self.path.b.clone(),
+            C => self.path.c.clone(),
+        }
+    }
+    fn adv(&mut self, value: Bits) {
+        self.path.a = self.builder.shr(&self.path.a, &value);
+    }
+    fn bxl(&mut self, value: u8) {
+        self.path.b = self
+            .builder
+            .xor_bits(&self.path.b, &constant(value.into(), WIDTH));
+    }
+    fn bst(&mut self, value: Bits) {
+        self.path.b = low_bits(&value);
+    }
+    fn jnz(&mut self, value: u8) {
+        self.jump = Some(value);
+    }
+    fn bxc(&mut self) {
+        self.path.b = self.builder.xor_bits(&self.path.b, &self.path.c);
+    }
+    fn out(&mut self, value: Bits) {
+        self.output = Some(low_bits(&value));
+    }
+    fn bdv(&mut self, value: Bits) {
+        self.path.b = self.builder.shr(&self.path.a, &value);
+    }
+    fn cdv(&mut self, value: Bits) {
+        self.path.c = self.builder.shr(&self.path.a, &value);
+    }
+}
+
+// The value modulo 8.
+fn low_bits(value: &[Lit]) -> Bits {
+    let mut bits = value[..3].to_vec();
+    bits.resize(WIDTH, Lit::FALSE);
+    bits
+}
+
+// The smallest value of `bits` under the conditions, fixing bits from the
+// most significant down.
+fn minimize(builder: &mut Builder, conditions: &[Lit], bits: &[Lit]) -> Option<u64> {
+    let mut assumptions = conditions.to_vec();
+    if !builder.solver.solve(&assumptions) {
+        return None;
+    }
+    for &bit in bits.iter().rev() {
+        if !builder.solver.model_value(bit)
+            || builder.solver.solve(&[&assumptions[..], &[!bit]].concat())
+        {
+            assumptions.push(!bit);
+        } else {
+            // The last model still satisfies the assumptions with this set.
+            assumptions.push(bit);
+        }
+    }
+    Some(builder.value(bits))
+}
+
+// Whether a path under `conditions` can go each way at a branch on `lit`.
+fn branches(builder: &mut Builder, conditions: &[Lit], lit: Lit) -> (bool, bool) {
+    if conditions.contains(&lit) {
+        (true, false)
+    } else if conditions.contains(&!lit) {
+        (false, true)
+    } else {
+        let mut assumptions = conditions.to_vec();
+        assumptions.push(lit);
+        let taken = builder.solver.solve(&assumptions);
+        *assumptions.last_mut().unwrap() = !lit;
+        (taken, builder.solver.solve(&assumptions))
+    }
+}
+
+// The values of A, smallest first, for which the program outputs exactly
+// `output`. Each value is found as it's asked for, as there may be as many
+// as 2^64 of them. If some path ran too long to follow, the values end with
+// an error once they reach the smallest A that path could have.
+pub fn find_all(
+    program: &Program,
+    output: &[u8],
+    b: u64,
+    c: u64,
+) -> impl Iterator<Item = Result<u64, SearchIncomplete>> + use<> {
+    let mut builder = Builder::new();
+    let a = builder.input(WIDTH);
+    let mut paths = vec![Path {
+        a: a.clone(),
+        b: constant(b, WIDTH),
+        c: constant(c, WIDTH),
+        instruction_pointer: 0,
+        outputs: 0,
+        steps: 0,
+        conditions: Vec::new(),
+    }];
+    // The conditions for each path that halts with the right output, and
+    // for each path that was abandoned.
+    let mut halting = Vec::new();
+    let mut abandoned = Vec::new();
+    'paths: while let Some(mut path) = paths.pop() {
+        loop {
+            let instruction = match fetch(program, path.instruction_pointer) {
+                Ok(Some(instruction)) => instruction,
+                Ok(None) => break,
+                // A path that faults never halts with the output.
+                Err(_) => continue 'paths,
+            };
+            if path.steps == STEP_LIMIT {
+                abandoned.push(path.conditions);
+                continue 'paths;
+            }
+            path.steps += 1;
+            path.instruction_pointer += 2;
+            let mut executor = Executor {
+                builder: &mut builder,
+                path: &mut path,
+                jump: None,
+                output: None,
+            };
+            executor.execute(instruction);
+            let (jump, out) = (executor.jump, executor.output);
+            if let Some(bits) = out {
+                let Some(&expected) = output.get(path.outputs) else {
+                    continue 'paths;
+                };
+                path.outputs += 1;
+                for (i, bit) in bits[..3].iter().enumerate() {
+                    let bit = if expected >> i & 1 == 1 { *bit } else { !*bit };
+                    match bit {
+                        Lit::FALSE => continue 'paths,
+                        Lit::TRUE => (),
+                        _ => path.conditions.push(bit),
+                    }
+                }
+            }
+            if let Some(target) = jump {
+                match builder.any(&path.a) {
+                    Lit::FALSE => (),
+                    Lit::TRUE => path.instruction_pointer = target.into(),
+                    nonzero => match branches(&mut builder, &path.conditions, nonzero) {
+                        (true, true) => {
+                            let mut taken = path.clone();
+                            taken.conditions.push(nonzero);
+                            taken.instruction_pointer = target.into();
+                            paths.push(taken);
+                            path.conditions.push(!nonzero);
+                        }
+                        (true, false) => {
+                            if !path.conditions.contains(&nonzero) {
+                                path.conditions.push(nonzero);
+                            }
+                            path.instruction_pointer = target.into();
+                        }
+                        (false, true) => {
+                            if !path.conditions.contains(&!nonzero) {
+                                path.conditions.push(!nonzero);
+                            }
+                        }
+                        // The conditions contradict each other.
+                        (false, false) => continue 'paths,
+                    },
+                }
+            }
+        }
+        if path.outputs == output.len() {
+            halting.push(path.conditions);
+        }
+    }
+    let incomplete_from = (abandoned.iter())
+        .filter_map(|conditions| minimize(&mut builder, conditions, &a))
+        .min();
+    // The smallest value of A left on each halting path.
+    let mut smallest: Vec<Option<u64>> = (halting.iter())
+        .map(|conditions| minimize(&mut builder, conditions, &a))
+        .collect();
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let value = smallest.iter().flatten().min().copied();
+        let Some(value) = value.filter(|&value| incomplete_from.is_none_or(|from| value < from))
+        else {
+            done = true;
+            return incomplete_from.map(|from| Err(SearchIncomplete { from }));
+        };
+        // Rule the value out so the next call finds the next smallest, which
+        // only changes the paths that allowed it.
+        let differs: Vec<Lit> = (a.iter().enumerate())
+            .map(|(i, &bit)| if value >> i & 1 == 1 { !bit } else { bit })
+            .collect();
+        builder.solver.add_clause(&differs);
+        for (conditions, min) in halting.iter().zip(&mut smallest) {
+            if *min == Some(value) {
+                *min = minimize(&mut builder, conditions, &a);
+            }
+        }
+        Some(Ok(value))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::machine::{Registers, run_program};
+
+    // At most `len + 1` outputs, as some programs never halt.
+    fn output(program: &Program, a: u64, len: usize) -> Vec<u8> {
+        let mut registers = Registers { a, b: 0, c: 0 };
+        run_program(&mut registers, program).take(len + 1).collect()
+    }
+
+    fn find_a(program: &Program, output: &[u8], b: u64, c: u64) -> Option<u64> {
+        find_all(program, output, b, c).next().map(Result::unwrap)
+    }
+
+    #[test]
+    fn test_quine() {
+        let program = [0, 3, 5, 4, 3, 0];
+        assert_eq!(find_a(&program, &program, 0, 0), Some(117440));
+    }
+
+    #[test]
+    fn test_variable_shift() {
+        // Shifts A by a data-dependent amount and jumps into the middle.
+        let program = assemble(
+            "
+                bxl 1
+            loop:
+                bst a
+                bxl 5
+                cdv b
+                bxc
+                out b
+                adv b
+                jnz loop
+            ",
+        )
+        .unwrap();
+        let target = [2, 4, 6];
+        assert_eq!(find_a(&program, &target, 0, 0), Some(224));
+        assert_eq!(output(&program, 224, 3), target);
+        assert!((0..224).all(|a| output(&program, a, 3) != target));
+    }
+
+    #[test]
+    fn test_find_all() {
+        let program = [0, 3, 5, 4, 3, 0];
+        assert!(find_all(&program, &program, 0, 0).eq((117440..117448).map(Ok)));
+        // Output B ^ C, whatever A is.
+        let program = assemble("bxc\nout b").unwrap();
+        assert!(
+            find_all(&program, &[6], 2, 4)
+                .take(3)
+                .eq([Ok(0), Ok(1), Ok(2)])
+        );
+    }
+
+    #[test]
+    fn test_incomplete() {
+        // Loops forever unless A is zero, without ever changing A.
+        let program = assemble("l: jnz l").unwrap();
+        assert!(find_all(&program, &[], 0, 0).eq([Ok(0), Err(SearchIncomplete { from: 1 })]));
+    }
+
+    #[test]
+    fn test_no_solution() {
+        // The output only ever depends on the constant B.
+        let program = assemble("bxl 3\nout b").unwrap();
+        assert_eq!(find_a(&program, &[3], 0, 0), Some(0));
+        assert_eq!(find_a(&program, &[4], 0, 0), None);
+    }
+}
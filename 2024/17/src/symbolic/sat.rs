@@ -0,0 +1,422 @@
+// A small CDCL SAT solver: two watched literals, first-UIP clause learning,
+// VSIDS branching with phase saving, and geometric restarts. Solving under
+// assumptions lets callers ask incremental questions of the same clauses.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    // Variable 0 is fixed to true.
+    pub const TRUE: Lit = Lit(0);
+    pub const FALSE: Lit = Lit(1);
+
+    fn new(var: usize, negated: bool) -> Self {
+        Lit((var as u32) << 1 | u32::from(negated))
+    }
+
+    fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Value {
+    Unassigned,
+    True,
+    False,
+}
+
+// A variable and its activity, ordered by activity.
+#[derive(PartialEq)]
+struct Activity(f64, usize);
+
+impl Eq for Activity {}
+
+impl PartialOrd for Activity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Activity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    // Clauses watching each literal, indexed by `Lit::index`.
+    watches: Vec<Vec<usize>>,
+    values: Vec<Value>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    activity_increment: f64,
+    order: BinaryHeap<Activity>,
+    phase: Vec<bool>,
+    seen: Vec<bool>,
+    model: Vec<bool>,
+    // False once the clauses are known to be unsatisfiable.
+    ok: bool,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        let mut solver = Self {
+            clauses: Vec::new(),
+            watches: Vec::new(),
+            values: Vec::new(),
+            levels: Vec::new(),
+            reasons: Vec::new(),
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            propagated: 0,
+            activity: Vec::new(),
+            activity_increment: 1.0,
+            order: BinaryHeap::new(),
+            phase: Vec::new(),
+            seen: Vec::new(),
+            model: Vec::new(),
+            ok: true,
+        };
+        let var = solver.new_var();
+        solver.add_clause(&[var]);
+        solver
+    }
+
+    pub fn new_var(&mut self) -> Lit {
+        let var = self.values.len();
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.values.push(Value::Unassigned);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.order.push(Activity(0.0, var));
+        self.phase.push(false);
+        self.seen.push(false);
+        Lit::new(var, false)
+    }
+
+    fn value(&self, lit: Lit) -> Value {
+        match (self.values[lit.var()], lit.is_negated()) {
+            (Value::Unassigned, _) => Value::Unassigned,
+            (Value::True, false) | (Value::False, true) => Value::True,
+            _ => Value::False,
+        }
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.values[var] = if lit.is_negated() {
+            Value::False
+        } else {
+            Value::True
+        };
+        self.levels[var] = self.decision_level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let limit = self.trail_limits[level];
+        for lit in self.trail.drain(limit..) {
+            let var = lit.var();
+            self.phase[var] = !lit.is_negated();
+            self.values[var] = Value::Unassigned;
+            self.reasons[var] = None;
+            self.order.push(Activity(self.activity[var], var));
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.propagated.min(limit);
+    }
+
+    fn watch(&mut self, clause: usize) {
+        let [first, second, ..] = self.clauses[clause][..] else {
+            unreachable!("watched clauses have at least two literals")
+        };
+        self.watches[first.index()].push(clause);
+        self.watches[second.index()].push(clause);
+    }
+
+    // Adds a clause. Must not be called while solving.
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        if !self.ok {
+            return;
+        }
+        let mut clause: Vec<Lit> = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Value::True => return,
+                Value::False => continue,
+                Value::Unassigned if clause.contains(&!lit) => return,
+                Value::Unassigned if !clause.contains(&lit) => clause.push(lit),
+                Value::Unassigned => (),
+            }
+        }
+        match clause[..] {
+            [] => self.ok = false,
+            [lit] => {
+                self.assign(lit, None);
+                self.ok = self.propagate().is_none();
+            }
+            _ => {
+                self.clauses.push(clause);
+                self.watch(self.clauses.len() - 1);
+            }
+        }
+    }
+
+    // Unit propagation. Returns a conflicting clause, if any.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let falsified = !self.trail[self.propagated];
+            self.propagated += 1;
+            let mut watching = std::mem::take(&mut self.watches[falsified.index()]);
+            let mut i = 0;
+            while i < watching.len() {
+                let clause = watching[i];
+                // Keep the falsified literal in position 1.
+                if self.clauses[clause][0] == falsified {
+                    self.clauses[clause].swap(0, 1);
+                }
+                let first = self.clauses[clause][0];
+                if self.value(first) == Value::True {
+                    i += 1;
+                    continue;
+                }
+                let replacement = (2..self.clauses[clause].len())
+                    .find(|&k| self.value(self.clauses[clause][k]) != Value::False);
+                if let Some(k) = replacement {
+                    self.clauses[clause].swap(1, k);
+                    let lit = self.clauses[clause][1];
+                    self.watches[lit.index()].push(clause);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if self.value(first) == Value::False {
+                    self.watches[falsified.index()] = watching;
+                    self.propagated = self.trail.len();
+                    return Some(clause);
+                }
+                self.assign(first, Some(clause));
+                i += 1;
+            }
+            self.watches[falsified.index()] = watching;
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_increment;
+        if self.activity[var] > 1e100 {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.activity_increment *= 1e-100;
+            self.order = (0..self.activity.len())
+                .map(|var| Activity(self.activity[var], var))
+                .collect();
+        }
+        self.order.push(Activity(self.activity[var], var));
+    }
+
+    // First-UIP conflict analysis. Returns the learnt clause, asserting
+    // literal first, and the level to backtrack to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![Lit::TRUE];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        let mut skip_first = false;
+        loop {
+            for k in usize::from(skip_first)..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if !self.seen[var] && self.levels[var] > 0 {
+                    self.seen[var] = true;
+                    self.bump(var);
+                    if self.levels[var] == self.decision_level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+            let lit = loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break self.trail[index];
+                }
+            };
+            self.seen[lit.var()] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = !lit;
+                break;
+            }
+            clause = self.reasons[lit.var()].unwrap();
+            skip_first = true;
+        }
+        for lit in &learnt[1..] {
+            self.seen[lit.var()] = false;
+        }
+        let mut level = 0;
+        if learnt.len() > 1 {
+            let (k, _) = (1..learnt.len())
+                .map(|k| (k, self.levels[learnt[k].var()]))
+                .max_by_key(|&(_, level)| level)
+                .unwrap();
+            learnt.swap(1, k);
+            level = self.levels[learnt[1].var()];
+        }
+        (learnt, level)
+    }
+
+    fn pick_branch(&mut self) -> Option<Lit> {
+        while let Some(Activity(_, var)) = self.order.pop() {
+            if self.values[var] == Value::Unassigned {
+                return Some(Lit::new(var, !self.phase[var]));
+            }
+        }
+        None
+    }
+
+    // Searches for an assignment that satisfies every clause and every
+    // assumption. On success it is available through `model_value`.
+    pub fn solve(&mut self, assumptions: &[Lit]) -> bool {
+        let result = self.search(assumptions);
+        self.backtrack(0);
+        result
+    }
+
+    fn search(&mut self, assumptions: &[Lit]) -> bool {
+        if !self.ok {
+            return false;
+        }
+        let mut conflicts = 0;
+        let mut restart_limit = 100.0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.decision_level() == 0 {
+                    self.ok = false;
+                    return false;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if let [lit] = learnt[..] {
+                    self.assign(lit, None);
+                } else {
+                    let asserting = learnt[0];
+                    self.clauses.push(learnt);
+                    let clause = self.clauses.len() - 1;
+                    self.watch(clause);
+                    self.assign(asserting, Some(clause));
+                }
+                self.activity_increment /= 0.95;
+                conflicts += 1;
+                if f64::from(conflicts) > restart_limit {
+                    conflicts = 0;
+                    restart_limit *= 1.5;
+                    self.backtrack(0);
+                }
+                continue;
+            }
+            let lit = if let Some(&assumption) = assumptions.get(self.decision_level()) {
+                match self.value(assumption) {
+                    Value::True => {
+                        // Already implied; open an empty level to keep
+                        // levels and assumptions in step.
+                        self.trail_limits.push(self.trail.len());
+                        continue;
+                    }
+                    Value::False => return false,
+                    Value::Unassigned => assumption,
+                }
+            } else if let Some(lit) = self.pick_branch() {
+                lit
+            } else {
+                self.model = self
+                    .values
+                    .iter()
+                    .map(|&value| value == Value::True)
+                    .collect();
+                return true;
+            };
+            self.trail_limits.push(self.trail.len());
+            self.assign(lit, None);
+        }
+    }
+
+    // The value of `lit` in the last satisfying assignment.
+    pub fn model_value(&self, lit: Lit) -> bool {
+        self.model[lit.var()] != lit.is_negated()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pigeonhole() {
+        // Three pigeons don't fit in two holes.
+        let mut solver = Solver::new();
+        let p: Vec<[Lit; 2]> = (0..3)
+            .map(|_| [solver.new_var(), solver.new_var()])
+            .collect();
+        for pigeon in &p {
+            solver.add_clause(pigeon);
+        }
+        for hole in 0..2 {
+            for (i, first) in p.iter().enumerate() {
+                for second in &p[i + 1..] {
+                    solver.add_clause(&[!first[hole], !second[hole]]);
+                }
+            }
+        }
+        assert!(!solver.solve(&[]));
+    }
+
+    #[test]
+    fn test_assumptions() {
+        let mut solver = Solver::new();
+        let (a, b, c) = (solver.new_var(), solver.new_var(), solver.new_var());
+        solver.add_clause(&[!a, b]);
+        solver.add_clause(&[!b, c]);
+        assert!(solver.solve(&[a]));
+        assert!(solver.model_value(c));
+        assert!(!solver.solve(&[a, !c]));
+        assert!(solver.solve(&[!c]));
+        assert!(!solver.model_value(a));
+    }
+}
@@ -14,11 +14,12 @@ pub struct PrecomputedCosts<KP: Keypad>(pub HashMap<(KP::Key, KP::Key), usize>);
 
 impl<KP: Keypad> PrecomputedCosts<KP> {
     pub fn new(keypad: &KP, costs: &dyn Costs<KP>) -> Self {
+        let keys = keypad.keys();
         PrecomputedCosts(
-            enum_iterator::all::<KP::Key>()
-                .flat_map(|source| {
-                    enum_iterator::all::<KP::Key>()
-                        .map(move |target| ((source, target), costs.cost(keypad, source, target)))
+            keys.iter()
+                .flat_map(|&source| {
+                    keys.iter()
+                        .map(move |&target| ((source, target), costs.cost(keypad, source, target)))
                 })
                 .collect(),
         )
@@ -93,16 +94,15 @@ impl<C: Costs<DirectionalKeypad>> Robot<C> {
     }
 }
 
-impl<KP: Keypad, C: Costs<DirectionalKeypad>> Costs<KP> for Robot<C> {
-    // The cost for a robot to move from `source` to `target`,
-    // with the operator starting at `A` and returning to `A`.
-    // The operator of the robot must push an appropriate series of keys
-    // on the robot's keypad. This leaves the robot hovering over the key
-    // but not pushing it. To then press the button `n` times, add `n` to
-    // the result.
-    fn cost(&self, keypad: &KP, source: KP::Key, target: KP::Key) -> usize {
-        // Find the cost of the shortest path to the state where the
-        // robot is hovering over `target`.
+impl<C: Costs<DirectionalKeypad>> Robot<C> {
+    // The cheapest path from hovering over `source` to hovering over
+    // `target`, with the operator starting and ending at `A`.
+    fn path<KP: Keypad>(
+        &self,
+        keypad: &KP,
+        source: KP::Key,
+        target: KP::Key,
+    ) -> (Vec<State<KP::Key>>, usize) {
         let start: State<<KP as Keypad>::Key> = State {
             parent_key: DirectionalKeypad::ACTIVATE,
             key: source,
@@ -111,22 +111,63 @@ impl<KP: Keypad, C: Costs<DirectionalKeypad>> Costs<KP> for Robot<C> {
             parent_key: DirectionalKeypad::ACTIVATE,
             key: target,
         };
-        let (_, cost) = dijkstra(
+        dijkstra(
             &start,
             |&state| self.successors(keypad, state),
             |&state| state == target,
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    // The keys the operator pushes for the robot to move from `source` to
+    // `target` and press it. Steps where the parent key stays put are
+    // pushes; the rest only move the operator.
+    pub fn presses<KP: Keypad>(
+        &self,
+        keypad: &KP,
+        source: KP::Key,
+        target: KP::Key,
+    ) -> Vec<directional_keypad::Key> {
+        let (path, _) = self.path(keypad, source, target);
+        let mut presses: Vec<directional_keypad::Key> = path
+            .windows(2)
+            .filter(|step| step[0].parent_key == step[1].parent_key)
+            .map(|step| step[0].parent_key)
+            .collect();
+        presses.push(DirectionalKeypad::ACTIVATE);
+        presses
+    }
+}
+
+impl<KP: Keypad, C: Costs<DirectionalKeypad>> Costs<KP> for Robot<C> {
+    // The cost for a robot to move from `source` to `target`,
+    // with the operator starting at `A` and returning to `A`.
+    // The operator of the robot must push an appropriate series of keys
+    // on the robot's keypad. This leaves the robot hovering over the key
+    // but not pushing it. To then press the button `n` times, add `n` to
+    // the result.
+    fn cost(&self, keypad: &KP, source: KP::Key, target: KP::Key) -> usize {
+        // Find the cost of the shortest path to the state where the
+        // robot is hovering over `target`.
+        let (_, cost) = self.path(keypad, source, target);
         cost
     }
 }
 
-pub fn robot_stack(n: usize) -> Robot<PrecomputedCosts<DirectionalKeypad>> {
+// Every robot in a stack of `n`, starting with the one the human operates.
+pub fn robot_chain(n: usize) -> Vec<Robot<PrecomputedCosts<DirectionalKeypad>>> {
     let human: &dyn Costs<DirectionalKeypad> = &Human::new();
-    let mut robot: Robot<PrecomputedCosts<DirectionalKeypad>> =
-        Robot::new(PrecomputedCosts::new(&DirectionalKeypad, human));
+    let mut chain = vec![Robot::new(PrecomputedCosts::new(&DirectionalKeypad, human))];
     for _ in 1..n {
-        robot = Robot::new(PrecomputedCosts::new(&DirectionalKeypad, &robot));
+        let robot = Robot::new(PrecomputedCosts::new(
+            &DirectionalKeypad,
+            chain.last().unwrap(),
+        ));
+        chain.push(robot);
     }
-    robot
+    chain
+}
+
+pub fn robot_stack(n: usize) -> Robot<PrecomputedCosts<DirectionalKeypad>> {
+    robot_chain(n).pop().unwrap()
 }
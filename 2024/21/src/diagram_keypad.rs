@@ -0,0 +1,241 @@
+use crate::direction::Direction;
+use crate::keypad::Keypad;
+use parse_display::Display;
+use std::str::FromStr;
+
+const MAX: usize = 8;
+
+// A keypad whose layout is read at runtime from a diagram, either the
+// boxed drawings in the other keypad modules or one character per key:
+//
+//  ^A
+// <v>
+//
+// Gaps are spaces. Every key is a printable ASCII character, there must be
+// an `A` key to activate, and every key must be reachable from every other.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct DiagramKeypad {
+    // Key labels by row and column, with 0 for a gap.
+    cells: [[u8; MAX]; MAX],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Label(u8);
+
+const FIRST: u8 = b'!';
+const LAST: u8 = b'~';
+
+impl Default for Label {
+    fn default() -> Self {
+        Label(b'A')
+    }
+}
+
+impl std::fmt::Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&char::from(*self), f)
+    }
+}
+
+#[derive(Debug, Display)]
+#[display("invalid keypad diagram")]
+pub struct ParseError;
+
+impl TryFrom<char> for Label {
+    type Error = ParseError;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        u8::try_from(value)
+            .ok()
+            .filter(|byte| (FIRST..=LAST).contains(byte))
+            .map(Label)
+            .ok_or(ParseError)
+    }
+}
+
+impl From<Label> for char {
+    fn from(value: Label) -> Self {
+        value.0.into()
+    }
+}
+
+// A border line of a boxed diagram, like `+---+---+`. No row of a compact
+// diagram looks like this, as it would repeat the `-` key.
+fn is_border(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 1 && line.split('+').all(|part| part.is_empty() || part == "---")
+}
+
+impl DiagramKeypad {
+    // Whether every key can be reached from `A` without crossing a gap.
+    fn connected(&self) -> bool {
+        let mut seen = vec![Self::ACTIVATE];
+        let mut todo = vec![Self::ACTIVATE];
+        while let Some(key) = todo.pop() {
+            for direction in enum_iterator::all::<Direction>() {
+                if let Some(next) = self.next_key(key, direction)
+                    && !seen.contains(&next)
+                {
+                    seen.push(next);
+                    todo.push(next);
+                }
+            }
+        }
+        seen.len() == self.keys().len()
+    }
+}
+
+impl FromStr for DiagramKeypad {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let boxed = s.lines().any(is_border);
+        let rows: Vec<Vec<char>> = s
+            .lines()
+            .filter(|line| !boxed || line.contains('|'))
+            .map(|line| {
+                let chars: Vec<char> = line.chars().collect();
+                if boxed {
+                    // Labels sit in the middle of `| x |`, every four columns.
+                    chars.iter().skip(2).step_by(4).copied().collect()
+                } else {
+                    chars
+                }
+            })
+            .skip_while(|row| row.iter().all(|&c| c == ' '))
+            .collect();
+        let rows = match rows.iter().rposition(|row| row.iter().any(|&c| c != ' ')) {
+            Some(last) => &rows[..=last],
+            None => return Err(ParseError),
+        };
+        let mut keypad = DiagramKeypad::default();
+        for (row, chars) in rows.iter().enumerate() {
+            for (col, &c) in chars.iter().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                if row >= MAX || col >= MAX {
+                    return Err(ParseError);
+                }
+                let label = Label::try_from(c)?;
+                if keypad.pos(label).is_some() {
+                    return Err(ParseError);
+                }
+                keypad.cells[row][col] = label.0;
+            }
+        }
+        if keypad.pos(Self::ACTIVATE).is_none() || !keypad.connected() {
+            return Err(ParseError);
+        }
+        Ok(keypad)
+    }
+}
+
+impl Keypad for DiagramKeypad {
+    type Key = Label;
+    const ACTIVATE: Self::Key = Label(b'A');
+
+    fn pos(&self, key: Self::Key) -> Option<(usize, usize)> {
+        (0..MAX)
+            .flat_map(|row| (0..MAX).map(move |col| (row, col)))
+            .find(|&(row, col)| self.cells[row][col] == key.0)
+    }
+
+    fn at(&self, (row, col): (usize, usize)) -> Option<Self::Key> {
+        let &label = self.cells.get(row)?.get(col)?;
+        (label != 0).then_some(Label(label))
+    }
+
+    fn keys(&self) -> Vec<Self::Key> {
+        (self.cells.iter().flatten())
+            .filter(|&&label| label != 0)
+            .map(|&label| Label(label))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::costs::{Costs, PrecomputedCosts, robot_stack};
+    use crate::data::EXAMPLE1;
+    use crate::directional_keypad::DirectionalKeypad;
+    use crate::numeric_keypad::NumericKeypad;
+
+    const NUMERIC: &str = "
++---+---+---+
+| 7 | 8 | 9 |
++---+---+---+
+| 4 | 5 | 6 |
++---+---+---+
+| 1 | 2 | 3 |
++---+---+---+
+    | 0 | A |
+    +---+---+
+";
+
+    #[test]
+    fn test_parse_boxed() {
+        let keypad: DiagramKeypad = NUMERIC.parse().unwrap();
+        for key in NumericKeypad.keys() {
+            let label = Label::try_from(char::from(key)).unwrap();
+            assert_eq!(keypad.pos(label), NumericKeypad.pos(key));
+        }
+        assert_eq!(keypad.keys().len(), 11);
+        assert_eq!(keypad.at((3, 0)), None);
+        assert_eq!(keypad.pos(Label(b'B')), None);
+    }
+
+    #[test]
+    fn test_parse_compact() {
+        let keypad: DiagramKeypad = " ^A\n<v>".parse().unwrap();
+        assert_eq!(keypad.pos(Label(b'^')), Some((0, 1)));
+        assert_eq!(keypad.at((1, 2)), Some(Label(b'>')));
+        assert!("^^A".parse::<DiagramKeypad>().is_err());
+        assert!("123".parse::<DiagramKeypad>().is_err());
+        // `|` is a key, not a box.
+        let keypad: DiagramKeypad = "|A".parse().unwrap();
+        assert_eq!(keypad.pos(Label(b'|')), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_parse_disconnected() {
+        assert!("A 1".parse::<DiagramKeypad>().is_err());
+        assert!("A\n\n1".parse::<DiagramKeypad>().is_err());
+        assert!("A \n 1".parse::<DiagramKeypad>().is_err());
+        let boxed = "+---+   +---+\n| A |   | 1 |\n+---+   +---+";
+        assert!(boxed.parse::<DiagramKeypad>().is_err());
+    }
+
+    #[test]
+    fn test_same_costs_as_numeric() {
+        let keypad: DiagramKeypad = NUMERIC.parse().unwrap();
+        let robot = robot_stack(3);
+        let mut total = 0;
+        for line in EXAMPLE1.lines() {
+            let mut key = DiagramKeypad::ACTIVATE;
+            let mut presses = 0;
+            for c in line.chars() {
+                let next = Label::try_from(c).unwrap();
+                presses += robot.cost(&keypad, key, next) + 1;
+                key = next;
+            }
+            total += presses * line[..3].parse::<usize>().unwrap();
+        }
+        assert_eq!(total, 126384);
+    }
+
+    #[test]
+    fn test_precomputed_costs() {
+        // A robot stack can drive a directional keypad read from a diagram.
+        let keypad: DiagramKeypad = " ^A\n<v>".parse().unwrap();
+        let robot = robot_stack(2);
+        let costs = PrecomputedCosts::new(&keypad, &robot);
+        assert_eq!(costs.0.len(), 25);
+        let expected = PrecomputedCosts::new(&DirectionalKeypad, &robot);
+        for (&(source, target), &cost) in &expected.0 {
+            let label = |key| Label::try_from(char::from(key)).unwrap();
+            assert_eq!(costs.0[&(label(source), label(target))], cost);
+        }
+    }
+}
@@ -32,16 +32,16 @@ impl Keypad for DirectionalKeypad {
     type Key = Key;
     const ACTIVATE: Self::Key = Key::A;
 
-    fn pos(&self, key: Self::Key) -> (usize, usize) {
+    fn pos(&self, key: Self::Key) -> Option<(usize, usize)> {
         use Direction::*;
         use Key::*;
-        match key {
+        Some(match key {
             Move(U) => (0, 1),
             A => (0, 2),
             Move(L) => (1, 0),
             Move(D) => (1, 1),
             Move(R) => (1, 2),
-        }
+        })
     }
 
     fn at(&self, pos: (usize, usize)) -> Option<Self::Key> {
@@ -56,4 +56,40 @@ impl Keypad for DirectionalKeypad {
             _ => return None,
         })
     }
+
+    fn keys(&self) -> Vec<Self::Key> {
+        enum_iterator::all::<Key>().collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError;
+
+impl TryFrom<char> for Key {
+    type Error = ParseError;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        use Direction::*;
+        Ok(match value {
+            '^' => Key::Move(U),
+            'v' => Key::Move(D),
+            '<' => Key::Move(L),
+            '>' => Key::Move(R),
+            'A' => Key::A,
+            _ => return Err(ParseError),
+        })
+    }
+}
+
+impl From<Key> for char {
+    fn from(value: Key) -> Self {
+        use Direction::*;
+        match value {
+            Key::Move(U) => '^',
+            Key::Move(D) => 'v',
+            Key::Move(L) => '<',
+            Key::Move(R) => '>',
+            Key::A => 'A',
+        }
+    }
 }
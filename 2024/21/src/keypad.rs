@@ -1,16 +1,19 @@
 use crate::direction::Direction;
-use enum_iterator::Sequence;
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
 pub trait Keypad: Clone + Copy + Eq + Hash + Debug + Default {
-    type Key: Copy + Clone + Default + PartialEq + Eq + Hash + Display + Debug + Sequence;
+    type Key: Copy + Clone + Default + PartialEq + Eq + Hash + Display + Debug;
     const ACTIVATE: Self::Key;
 
-    fn pos(&self, key: Self::Key) -> (usize, usize);
+    // Where a key is, or `None` if it isn't on this keypad.
+    fn pos(&self, key: Self::Key) -> Option<(usize, usize)>;
     fn at(&self, pos: (usize, usize)) -> Option<Self::Key>;
 
+    // The keys on this keypad.
+    fn keys(&self) -> Vec<Self::Key>;
+
     fn next_key(&self, key: Self::Key, direction: Direction) -> Option<Self::Key> {
-        self.at(direction.apply(self.pos(key))?)
+        self.at(direction.apply(self.pos(key)?)?)
     }
 }
@@ -1,15 +1,107 @@
 mod costs;
 mod data;
+mod diagram_keypad;
 mod direction;
 mod directional_keypad;
 mod keypad;
 mod numeric_keypad;
 mod part1;
 mod part2;
+mod presses;
 mod puzzle;
+mod tropical;
 
 fn main() {
     use data::INPUT;
-    println!("Part 1: {}", part1::run(INPUT));
-    println!("Part 2: {}", part2::run(INPUT));
+    match std::env::args().nth(1).as_deref() {
+        // Print the costs between the keys of a keypad drawn on stdin, as
+        // operated by a stack of the given number of robots.
+        Some("costs") => {
+            use keypad::Keypad;
+            let robots: usize = std::env::args()
+                .nth(2)
+                .map_or(2, |n| n.parse().expect("parse failed"));
+            let diagram = std::io::read_to_string(std::io::stdin()).expect("read failed");
+            let keypad: diagram_keypad::DiagramKeypad = match diagram.parse() {
+                Ok(keypad) => keypad,
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            };
+            let costs = costs::PrecomputedCosts::new(&keypad, &costs::robot_stack(robots));
+            let keys = keypad.keys();
+            print!(" ");
+            for target in &keys {
+                print!(" {target:>4}");
+            }
+            println!();
+            for &source in &keys {
+                print!("{source}");
+                for &target in &keys {
+                    print!(" {:>4}", costs.0[&(source, target)]);
+                }
+                println!();
+            }
+        }
+        // Print the presses at every layer for a code typed through a
+        // stack of the given number of robots.
+        Some("presses") => {
+            let code: puzzle::Code = std::env::args()
+                .nth(2)
+                .unwrap_or("029A".to_string())
+                .parse()
+                .expect("parse failed");
+            let robots: usize = std::env::args()
+                .nth(3)
+                .map_or(3, |n| n.parse().expect("parse failed"));
+            let presses = match presses::Presses::new(&code, &costs::robot_chain(robots)) {
+                Ok(presses) => presses,
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            };
+            print!("{presses}");
+            if let Err(err) = presses.verify() {
+                println!("verification failed: {err}");
+            }
+        }
+        // Print the transition matrix for the robot at the top of a stack
+        // of the given number of robots, over (operator key, key) states.
+        Some("matrix") => {
+            let robots: usize = std::env::args()
+                .nth(2)
+                .map_or(1, |n| n.parse().expect("parse failed"));
+            let operator = match tropical::operator_costs(robots) {
+                Ok(operator) => operator,
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            };
+            let matrix =
+                tropical::transition_matrix(&directional_keypad::DirectionalKeypad, &operator);
+            print!("{matrix}");
+        }
+        // Solve the puzzle for a stack of any number of robots, with the
+        // deep layers extrapolated rather than computed one by one.
+        Some("stack") => {
+            let robots: usize = std::env::args()
+                .nth(2)
+                .map_or(10_000, |n| n.parse().expect("parse failed"));
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            match tropical::complexity(&puzzle, robots) {
+                Ok(total) => println!("{robots} robots: {total}"),
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            println!("Part 1: {}", part1::run(INPUT));
+            println!("Part 2: {}", part2::run(INPUT));
+        }
+    }
 }
@@ -21,14 +21,14 @@ impl Keypad for NumericKeypad {
     type Key = Key;
     const ACTIVATE: Self::Key = Key::A;
 
-    fn pos(&self, key: Self::Key) -> (usize, usize) {
+    fn pos(&self, key: Self::Key) -> Option<(usize, usize)> {
         use Key::*;
-        match key {
+        Some(match key {
             Digit(d @ (1..=9)) => (2 - (d as usize - 1) / 3, (d as usize - 1) % 3),
             Digit(0) => (3, 1),
             A => (3, 2),
-            _ => panic!("invalid key"),
-        }
+            _ => return None,
+        })
     }
 
     fn at(&self, pos: (usize, usize)) -> Option<Self::Key> {
@@ -40,6 +40,10 @@ impl Keypad for NumericKeypad {
             _ => return None,
         })
     }
+
+    fn keys(&self) -> Vec<Self::Key> {
+        enum_iterator::all::<Key>().collect()
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Display, Debug, Default)]
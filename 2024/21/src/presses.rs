@@ -0,0 +1,380 @@
+use crate::costs::{PrecomputedCosts, Robot};
+use crate::directional_keypad::{DirectionalKeypad, Key};
+use crate::keypad::Keypad;
+use crate::numeric_keypad::NumericKeypad;
+use crate::puzzle::Code;
+use crate::tropical::StackError;
+use parse_display::Display;
+use std::collections::HashMap;
+use std::fmt::{Formatter, Write};
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum SimulationError {
+    #[display("an arm moved over a gap")]
+    Gap,
+    #[display("the presses typed {0}")]
+    Mistyped(String),
+}
+
+// `count` presses of the same key in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Run {
+    pub key: Key,
+    pub count: usize,
+}
+
+impl std::fmt::Display for Run {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char(char::from(self.key))?;
+        if self.count > 1 {
+            write!(f, "{}", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+fn run_length(keys: impl IntoIterator<Item = Key>) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for key in keys {
+        match runs.last_mut() {
+            Some(run) if run.key == key => run.count += 1,
+            _ => runs.push(Run { key, count: 1 }),
+        }
+    }
+    runs
+}
+
+// The key moves of consecutive runs, starting from `A`.
+fn transitions(runs: &[Run]) -> impl Iterator<Item = (Key, Key)> + '_ {
+    let starts = std::iter::once(DirectionalKeypad::ACTIVATE).chain(runs.iter().map(|run| run.key));
+    (starts.zip(runs)).flat_map(|(from, run)| {
+        std::iter::once((from, run.key))
+            .chain(std::iter::repeat_n((run.key, run.key), run.count - 1))
+    })
+}
+
+// Push the keys of a directional keypad with an arm that starts over
+// `start`, and return what it types.
+pub fn simulate<KP: Keypad>(
+    keypad: &KP,
+    start: KP::Key,
+    presses: impl IntoIterator<Item = Key>,
+) -> Result<Vec<KP::Key>, SimulationError> {
+    let mut key = start;
+    let mut typed = Vec::new();
+    for press in presses {
+        match press {
+            Key::Move(direction) => {
+                key = keypad
+                    .next_key(key, direction)
+                    .ok_or(SimulationError::Gap)?;
+            }
+            Key::A => typed.push(key),
+        }
+    }
+    Ok(typed)
+}
+
+// The button presses at every layer of a robot stack that types a code.
+// Layer 0 is the code and the last layer is what the human pushes. Each
+// layer is stored as the presses that type each key of the layer above
+// after the one before it, so even a stack of 26 robots stays small. This
+// is the compressed form of every layer, read through `first` and
+// `expansion`: layer 1 is the runs from `first` in order, and each later
+// layer replaces every move on the layer above with its expansion.
+#[derive(Debug)]
+pub struct Presses {
+    code: Code,
+    // The presses on layer 1 that type each key of the code.
+    first: Vec<Vec<Run>>,
+    // For later layers, the presses that type `to` after `from` on the
+    // layer above.
+    expansions: Vec<HashMap<(Key, Key), Vec<Run>>>,
+}
+
+impl Presses {
+    // `chain` starts with the robot the human operates, as from
+    // `robot_chain`, and ends with the one at the numeric keypad.
+    pub fn new(
+        code: &Code,
+        chain: &[Robot<PrecomputedCosts<DirectionalKeypad>>],
+    ) -> Result<Self, StackError> {
+        let (numeric, rest) = chain.split_last().ok_or(StackError::NoRobots)?;
+        let mut from = NumericKeypad::ACTIVATE;
+        let first = (code.into_iter())
+            .map(|&to| {
+                run_length(numeric.presses(&NumericKeypad, std::mem::replace(&mut from, to), to))
+            })
+            .collect();
+        let keys = DirectionalKeypad.keys();
+        let expansions = (rest.iter().rev())
+            .map(|robot| {
+                (keys.iter())
+                    .flat_map(|&from| keys.iter().map(move |&to| (from, to)))
+                    .map(|(from, to)| {
+                        let presses = robot.presses(&DirectionalKeypad, from, to);
+                        ((from, to), run_length(presses))
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            code: code.clone(),
+            first,
+            expansions,
+        })
+    }
+
+    // The number of directional layers.
+    pub fn depth(&self) -> usize {
+        self.expansions.len() + 1
+    }
+
+    fn check_directional(&self, layer: usize) {
+        assert!(
+            (1..=self.depth()).contains(&layer),
+            "layer {layer} is not a directional layer, which run from 1 to {}",
+            self.depth()
+        );
+    }
+
+    // The runs on layer 1 that type each key of the code after the one
+    // before it.
+    pub fn first(&self) -> &[Vec<Run>] {
+        &self.first
+    }
+
+    // The runs on `layer` that type `to` after `from` on the layer above,
+    // for a layer from 2 up to the depth.
+    pub fn expansion(&self, layer: usize, from: Key, to: Key) -> &[Run] {
+        self.check_directional(layer);
+        assert!(layer > 1, "layer 1 is typed by `first`");
+        &self.expansions[layer - 2][&(from, to)]
+    }
+
+    // The number of presses on a layer, from 0 up to the depth.
+    pub fn len(&self, layer: usize) -> u128 {
+        if layer == 0 {
+            return self.first.len() as u128;
+        }
+        self.check_directional(layer);
+        // Presses on `layer` per move on each layer above it, bottom up.
+        let keys = DirectionalKeypad.keys();
+        let mut lengths: HashMap<(Key, Key), u128> = (keys.iter())
+            .flat_map(|&from| keys.iter().map(move |&to| ((from, to), 1)))
+            .collect();
+        for expansion in self.expansions[..layer - 1].iter().rev() {
+            lengths = (expansion.iter())
+                .map(|(&pair, runs)| (pair, transitions(runs).map(|pair| lengths[&pair]).sum()))
+                .collect();
+        }
+        (self.first.iter())
+            .flat_map(|runs| transitions(runs))
+            .map(|pair| lengths[&pair])
+            .sum()
+    }
+
+    // Call `f` with each press on a layer of at least 1 until it returns
+    // false.
+    fn visit(&self, layer: usize, f: &mut impl FnMut(Key) -> bool) -> bool {
+        (self.first().iter())
+            .flat_map(|runs| transitions(runs))
+            .all(|pair| self.visit_move(1, pair, layer, f))
+    }
+
+    fn visit_move(
+        &self,
+        current: usize,
+        (from, to): (Key, Key),
+        layer: usize,
+        f: &mut impl FnMut(Key) -> bool,
+    ) -> bool {
+        if current == layer {
+            return f(to);
+        }
+        transitions(self.expansion(current + 1, from, to))
+            .all(|pair| self.visit_move(current + 1, pair, layer, f))
+    }
+
+    // Up to `limit` presses from the start of a directional layer.
+    pub fn prefix(&self, layer: usize, limit: usize) -> Vec<Key> {
+        self.check_directional(layer);
+        let mut keys = Vec::new();
+        if limit > 0 {
+            self.visit(layer, &mut |key| {
+                keys.push(key);
+                keys.len() < limit
+            });
+        }
+        keys
+    }
+
+    // A whole directional layer, run-length encoded, if it has at most
+    // `limit` presses. Longer layers are only kept compressed, through
+    // `first` and `expansion`.
+    pub fn runs(&self, layer: usize, limit: usize) -> Option<Vec<Run>> {
+        if self.len(layer) > limit as u128 {
+            return None;
+        }
+        Some(run_length(self.prefix(layer, limit)))
+    }
+
+    // Check by simulation that every layer types the one above it. Each
+    // stored piece of presses is replayed from the key it starts at, which
+    // covers every layer however long it is.
+    pub fn verify(&self) -> Result<(), SimulationError> {
+        let presses = self.first.iter().flat_map(|runs| expand(runs));
+        let typed = simulate(&NumericKeypad, NumericKeypad::ACTIVATE, presses)?;
+        if typed != (&self.code).into_iter().copied().collect::<Vec<_>>() {
+            let typed: String = typed.into_iter().map(char::from).collect();
+            return Err(SimulationError::Mistyped(typed));
+        }
+        for expansion in &self.expansions {
+            for (&(from, to), runs) in expansion {
+                let typed = simulate(&DirectionalKeypad, from, expand(runs))?;
+                if typed != [to] {
+                    let typed: String = typed.into_iter().map(char::from).collect();
+                    return Err(SimulationError::Mistyped(typed));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expand(runs: &[Run]) -> impl Iterator<Item = Key> + '_ {
+    runs.iter()
+        .flat_map(|run| std::iter::repeat_n(run.key, run.count))
+}
+
+// Each layer's length, then its presses where there are few enough.
+impl std::fmt::Display for Presses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "0: {}", self.code)?;
+        for layer in 1..=self.depth() {
+            write!(f, "{layer}: {} presses", self.len(layer))?;
+            if let Some(runs) = self.runs(layer, 80) {
+                write!(f, ": ")?;
+                for run in runs {
+                    write!(f, "{run}")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::costs::{robot_chain, robot_stack};
+    use crate::data::EXAMPLE1;
+    use crate::puzzle::Puzzle;
+
+    fn keys(s: &str) -> Vec<Key> {
+        s.chars().map(|c| Key::try_from(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let code: Code = "029A".parse().unwrap();
+        let presses = Presses::new(&code, &robot_chain(3)).unwrap();
+        assert_eq!(presses.depth(), 3);
+        assert_eq!(presses.len(1), 12);
+        assert_eq!(presses.len(2), 28);
+        assert_eq!(presses.len(3), 68);
+        presses.verify().unwrap();
+
+        // Simulating the human's presses types the code through every layer.
+        let human = presses.prefix(3, 1000);
+        let second = simulate(&DirectionalKeypad, Key::A, human).unwrap();
+        assert_eq!(second, presses.prefix(2, 1000));
+        let first = simulate(&DirectionalKeypad, Key::A, second).unwrap();
+        let typed = simulate(&NumericKeypad, NumericKeypad::ACTIVATE, first).unwrap();
+        assert_eq!(typed, (&code).into_iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_lengths_match_costs() {
+        let puzzle: Puzzle = EXAMPLE1.parse().unwrap();
+        for robots in [3, 26] {
+            let chain = robot_chain(robots);
+            let total: u128 = (puzzle.codes.iter())
+                .map(|code| {
+                    let presses = Presses::new(code, &chain).unwrap();
+                    presses.verify().unwrap();
+                    presses.len(robots) * code.numeric_part() as u128
+                })
+                .sum();
+            let expected = puzzle.solve_with_costs(&robot_stack(robots));
+            assert_eq!(total, expected as u128);
+        }
+    }
+
+    #[test]
+    fn test_runs() {
+        let code: Code = "029A".parse().unwrap();
+        let presses = Presses::new(&code, &robot_chain(26)).unwrap();
+        assert_eq!(presses.runs(26, 1000), None);
+        assert_eq!(presses.prefix(26, 5).len(), 5);
+        let runs = presses.runs(1, 100).unwrap();
+        assert_eq!(expand(&runs).collect::<Vec<_>>(), presses.prefix(1, 100));
+        assert_eq!(run_length(keys("<<^AA")).len(), 3);
+        assert_eq!(
+            run_length(keys("<<^AA"))
+                .iter()
+                .map(Run::to_string)
+                .collect::<String>(),
+            "<2^A2"
+        );
+    }
+
+    #[test]
+    fn test_compressed() {
+        // Expanding layer 26 from its compressed form gives its presses.
+        let code: Code = "029A".parse().unwrap();
+        let presses = Presses::new(&code, &robot_chain(26)).unwrap();
+        let mut layer: Vec<Key> = presses
+            .first()
+            .iter()
+            .flat_map(|runs| expand(runs))
+            .collect();
+        for n in 2..=4 {
+            let runs = run_length(layer);
+            layer = transitions(&runs)
+                .flat_map(|(from, to)| expand(presses.expansion(n, from, to)))
+                .collect();
+        }
+        assert_eq!(layer.len() as u128, presses.len(4));
+        assert_eq!(layer, presses.prefix(4, layer.len()));
+        assert_eq!(
+            presses.expansion(26, Key::A, Key::A),
+            [Run {
+                key: Key::A,
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_no_robots() {
+        let code: Code = "029A".parse().unwrap();
+        assert_eq!(Presses::new(&code, &[]).err(), Some(StackError::NoRobots));
+    }
+
+    #[test]
+    #[should_panic(expected = "layer 4 is not a directional layer")]
+    fn test_layer_out_of_range() {
+        let code: Code = "029A".parse().unwrap();
+        Presses::new(&code, &robot_chain(3)).unwrap().len(4);
+    }
+
+    #[test]
+    fn test_simulate_gap() {
+        assert_eq!(
+            simulate(&DirectionalKeypad, Key::A, keys("<<A")),
+            Err(SimulationError::Gap)
+        );
+    }
+}
@@ -9,7 +9,7 @@ use std::str::FromStr;
 #[derive(Debug)]
 pub struct ParseError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Code(Vec<Key>);
 
 impl IntoIterator for Code {
@@ -60,7 +60,7 @@ impl Display for Code {
 }
 
 impl Code {
-    fn numeric_part(&self) -> usize {
+    pub fn numeric_part(&self) -> usize {
         let s: String = self.0[0..self.0.len() - 1]
             .iter()
             .cloned()
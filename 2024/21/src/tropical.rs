@@ -0,0 +1,419 @@
+use crate::directional_keypad::DirectionalKeypad;
+use crate::keypad::Keypad;
+use crate::numeric_keypad::NumericKeypad;
+use crate::puzzle::Puzzle;
+use num::{BigUint, One, Zero};
+use parse_display::Display;
+use std::fmt::Formatter;
+
+// A square matrix over the (min, +) semiring, where addition is `min` and
+// multiplication is `+`. `None` is infinity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tropical {
+    size: usize,
+    cells: Vec<Option<BigUint>>,
+}
+
+impl Tropical {
+    pub fn infinity(size: usize) -> Self {
+        Self {
+            size,
+            cells: vec![None; size * size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> Option<&BigUint> {
+        self.cells[i * self.size + j].as_ref()
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: BigUint) {
+        self.cells[i * self.size + j] = Some(value);
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut product = Self::infinity(self.size);
+        for i in 0..self.size {
+            for k in 0..self.size {
+                let Some(a) = self.get(i, k) else {
+                    continue;
+                };
+                for j in 0..self.size {
+                    let Some(b) = other.get(k, j) else {
+                        continue;
+                    };
+                    let sum = a + b;
+                    if product.get(i, j).is_none_or(|best| sum < *best) {
+                        product.set(i, j, sum);
+                    }
+                }
+            }
+        }
+        product
+    }
+
+    // The cheapest paths of any length, I ⊕ A ⊕ A² ⊕ ..., found by squaring
+    // I ⊕ A until it covers paths through every node.
+    pub fn closure(&self) -> Self {
+        let mut closure = self.clone();
+        for i in 0..self.size {
+            closure.set(i, i, BigUint::zero());
+        }
+        let mut length = 1;
+        while length < self.size {
+            closure = closure.mul(&closure);
+            length *= 2;
+        }
+        closure
+    }
+}
+
+impl std::fmt::Display for Tropical {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for i in 0..self.size {
+            for j in 0..self.size {
+                if j > 0 {
+                    write!(f, " ")?;
+                }
+                match self.get(i, j) {
+                    Some(value) => write!(f, "{value:>3}")?,
+                    None => write!(f, "  .")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum StackError {
+    #[display("a stack needs at least one robot")]
+    NoRobots,
+    #[display("the shortest paths changed at a checked layer")]
+    Unsettled,
+}
+
+// The graph a robot on `keypad` moves through, with states for each pair
+// of operator key and robot key, numbered operator first. Pushing a move
+// key costs 1 and moving the operator costs what `operator` says.
+pub fn transition_matrix<KP: Keypad>(keypad: &KP, operator: &Tropical) -> Tropical {
+    let parents = DirectionalKeypad.keys();
+    let keys = keypad.keys();
+    let index = |parent: usize, key: usize| parent * keys.len() + key;
+    let mut matrix = Tropical::infinity(parents.len() * keys.len());
+    for (p, &parent) in parents.iter().enumerate() {
+        for (k, &key) in keys.iter().enumerate() {
+            if let crate::directional_keypad::Key::Move(direction) = parent
+                && let Some(next) = keypad.next_key(key, direction)
+            {
+                let next = keys.iter().position(|&key| key == next).unwrap();
+                matrix.set(index(p, k), index(p, next), BigUint::one());
+            }
+            for q in 0..parents.len() {
+                if let Some(cost) = operator.get(p, q)
+                    && q != p
+                {
+                    matrix.set(index(p, k), index(q, k), cost.clone());
+                }
+            }
+        }
+    }
+    matrix
+}
+
+fn activate() -> usize {
+    let parents = DirectionalKeypad.keys();
+    (parents.iter())
+        .position(|&key| key == DirectionalKeypad::ACTIVATE)
+        .unwrap()
+}
+
+// The costs for a robot on `keypad` to move between its keys, with the
+// operator starting and ending at `A`, given the operator's own costs.
+pub fn layer<KP: Keypad>(keypad: &KP, operator: &Tropical) -> Tropical {
+    let keys = keypad.keys().len();
+    let closure = transition_matrix(keypad, operator).closure();
+    let a = activate() * keys;
+    let mut costs = Tropical::infinity(keys);
+    for i in 0..keys {
+        for j in 0..keys {
+            if let Some(cost) = closure.get(a + i, a + j) {
+                costs.set(i, j, cost.clone());
+            }
+        }
+    }
+    costs
+}
+
+// How a directional layer's costs follow from the layer above along the
+// shortest paths that were picked: each cost is the operator costs it
+// uses, with multiplicity, plus its pushes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Affine {
+    uses: Vec<Vec<u32>>,
+    pushes: Vec<u32>,
+}
+
+fn affine(operator: &Tropical) -> Affine {
+    let keys = DirectionalKeypad.keys().len();
+    let matrix = transition_matrix(&DirectionalKeypad, operator);
+    let closure = matrix.clone().closure();
+    let states = matrix.size();
+    let a = activate() * keys;
+    let mut uses = vec![vec![0; keys * keys]; keys * keys];
+    let mut pushes = vec![0; keys * keys];
+    for target in 0..keys {
+        let t = a + target;
+        // Edges on a cheapest path to `t`. Moving the operator can be free,
+        // so among those, count the steps each state still needs.
+        let tight =
+            |u: usize, v: usize| match (matrix.get(u, v), closure.get(v, t), closure.get(u, t)) {
+                (Some(edge), Some(rest), Some(total)) => u != v && edge + rest == *total,
+                _ => false,
+            };
+        let mut steps = vec![usize::MAX; states];
+        steps[t] = 0;
+        let mut queue = std::collections::VecDeque::from([t]);
+        while let Some(v) = queue.pop_front() {
+            for u in 0..states {
+                if steps[u] == usize::MAX && tight(u, v) {
+                    steps[u] = steps[v] + 1;
+                    queue.push_back(u);
+                }
+            }
+        }
+        for source in 0..keys {
+            let row = source * keys + target;
+            let mut u = a + source;
+            // Take the fewest steps, lowest state first.
+            while u != t {
+                let v = (0..states)
+                    .find(|&v| steps[v] == steps[u] - 1 && tight(u, v))
+                    .unwrap();
+                let (p, q) = (u / keys, v / keys);
+                if p == q {
+                    pushes[row] += 1;
+                } else {
+                    uses[row][p * keys + q] += 1;
+                }
+                u = v;
+            }
+        }
+    }
+    Affine { uses, pushes }
+}
+
+type Matrix = Vec<Vec<BigUint>>;
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = vec![vec![BigUint::zero(); b[0].len()]; a.len()];
+    for (row, a_row) in product.iter_mut().zip(a) {
+        for (a, b_row) in a_row.iter().zip(b).filter(|(a, _)| !a.is_zero()) {
+            for (cell, b) in row.iter_mut().zip(b_row).filter(|(_, b)| !b.is_zero()) {
+                *cell += a * b;
+            }
+        }
+    }
+    product
+}
+
+// The affine map as one matrix acting on the costs with a 1 appended.
+fn augmented(affine: &Affine) -> Matrix {
+    let n = affine.pushes.len();
+    let mut matrix: Matrix = (affine.uses.iter().zip(&affine.pushes))
+        .map(|(uses, &pushes)| uses.iter().chain([&pushes]).map(|&c| c.into()).collect())
+        .collect();
+    matrix.push((0..=n).map(|j| BigUint::from(u8::from(j == n))).collect());
+    matrix
+}
+
+fn apply(matrix: &Matrix, costs: &Tropical) -> Tropical {
+    let n = costs.size();
+    let vector: Vec<BigUint> = (0..n * n)
+        .map(|i| costs.get(i / n, i % n).cloned().expect("unreachable key"))
+        .chain([BigUint::one()])
+        .collect();
+    let mut result = Tropical::infinity(n);
+    for (i, row) in matrix.iter().take(n * n).enumerate() {
+        let value = (row.iter().zip(&vector)).map(|(a, b)| a * b).sum();
+        result.set(i / n, i % n, value);
+    }
+    result
+}
+
+// Layers must pick the same paths this many times in a row before the
+// rest are extrapolated.
+const SETTLE: usize = 3;
+
+// The costs for the human at the bottom of a stack: every key is free.
+fn human() -> Tropical {
+    let size = DirectionalKeypad.keys().len();
+    let mut costs = Tropical::infinity(size);
+    for i in 0..size {
+        for j in 0..size {
+            costs.set(i, j, BigUint::zero());
+        }
+    }
+    costs
+}
+
+// The directional costs for the robot at the top of a stack of `n`, as
+// `PrecomputedCosts` would find them, extrapolated in logarithmic time.
+//
+// This is a heuristic, not a (min, +) matrix power: there is no fixed
+// matrix to raise, as each layer is the closure of a graph weighted by the
+// layer below. Once the same shortest paths have been picked for `SETTLE`
+// layers in a row, it assumes they stay shortest and applies the affine
+// map they define by repeated squaring with ordinary (+, ×) arithmetic.
+// Every layer the squaring lands on is checked to still pick those paths,
+// and the last one against an exact step, so a change of paths only goes
+// unnoticed if it is undone before the next checked layer. It agrees with
+// stepping every layer for the stacks in the tests.
+pub fn extrapolated_directional_costs(n: usize) -> Result<Tropical, StackError> {
+    if n == 0 {
+        return Err(StackError::NoRobots);
+    }
+    let mut costs = human();
+    let mut history: Vec<Affine> = Vec::new();
+    for k in 1..=n {
+        let step = affine(&costs);
+        if history.len() >= SETTLE && history[history.len() - SETTLE..].iter().all(|a| *a == step) {
+            // `costs` is layer k - 1; jump to layer n - 1 and step once.
+            let matrix = augmented(&step);
+            let mut power = matrix.clone();
+            let mut remaining = n - k;
+            while remaining > 0 {
+                if remaining & 1 == 1 {
+                    costs = apply(&power, &costs);
+                    if affine(&costs) != step {
+                        return Err(StackError::Unsettled);
+                    }
+                }
+                remaining >>= 1;
+                if remaining > 0 {
+                    power = mul(&power, &power);
+                }
+            }
+            let last = layer(&DirectionalKeypad, &costs);
+            if apply(&matrix, &costs) != last {
+                return Err(StackError::Unsettled);
+            }
+            return Ok(last);
+        }
+        costs = layer(&DirectionalKeypad, &costs);
+        history.push(step);
+    }
+    Ok(costs)
+}
+
+// The costs for whoever operates the robot at the top of a stack of `n`:
+// the human for a single robot, or else the stack below it, extrapolated.
+pub fn operator_costs(n: usize) -> Result<Tropical, StackError> {
+    match n {
+        0 => Err(StackError::NoRobots),
+        1 => Ok(human()),
+        _ => extrapolated_directional_costs(n - 1),
+    }
+}
+
+// The numeric keypad costs for a stack of `n` robots, extrapolated.
+pub fn numeric_costs(n: usize) -> Result<Tropical, StackError> {
+    Ok(layer(&NumericKeypad, &operator_costs(n)?))
+}
+
+// The puzzle answer for a stack of one or more robots, using costs
+// extrapolated by `extrapolated_directional_costs`.
+pub fn complexity(puzzle: &Puzzle, n: usize) -> Result<BigUint, StackError> {
+    let costs = numeric_costs(n)?;
+    let keys = NumericKeypad.keys();
+    let index = |key| keys.iter().position(|&k| k == key).unwrap();
+    let mut total = BigUint::zero();
+    for code in &puzzle.codes {
+        let mut from = index(NumericKeypad::ACTIVATE);
+        let mut presses = BigUint::zero();
+        for &key in code {
+            let to = index(key);
+            presses += costs.get(from, to).unwrap() + 1u8;
+            from = to;
+        }
+        total += presses * code.numeric_part();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::costs::{PrecomputedCosts, robot_chain, robot_stack};
+    use crate::data::{EXAMPLE1, INPUT};
+
+    fn exact(n: usize) -> Tropical {
+        let mut costs = human();
+        for _ in 0..n {
+            costs = layer(&DirectionalKeypad, &costs);
+        }
+        costs
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut a = Tropical::infinity(2);
+        a.set(0, 1, 3u8.into());
+        a.set(1, 0, 4u8.into());
+        let squared = a.mul(&a);
+        assert_eq!(squared.get(0, 0), Some(&7u8.into()));
+        assert_eq!(squared.get(0, 1), None);
+        let mut identity = Tropical::infinity(2);
+        identity.set(0, 0, 0u8.into());
+        identity.set(1, 1, 0u8.into());
+        assert_eq!(a.mul(&identity), a);
+        assert_eq!(a.closure().get(1, 1), Some(&0u8.into()));
+    }
+
+    #[test]
+    fn test_layers_match_robots() {
+        let keys = DirectionalKeypad.keys();
+        for (n, robot) in (1..).zip(robot_chain(8)) {
+            let costs = extrapolated_directional_costs(n).unwrap();
+            let expected = PrecomputedCosts::new(&DirectionalKeypad, &robot);
+            for (i, &source) in keys.iter().enumerate() {
+                for (j, &target) in keys.iter().enumerate() {
+                    let cost = &expected.0[&(source, target)];
+                    assert_eq!(costs.get(i, j), Some(&BigUint::from(*cost)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_jump_matches_layers() {
+        for n in [10, 100] {
+            assert_eq!(extrapolated_directional_costs(n), Ok(exact(n)));
+        }
+    }
+
+    #[test]
+    fn test_no_robots() {
+        let puzzle: Puzzle = EXAMPLE1.parse().unwrap();
+        assert_eq!(extrapolated_directional_costs(0), Err(StackError::NoRobots));
+        assert_eq!(numeric_costs(0), Err(StackError::NoRobots));
+        assert_eq!(complexity(&puzzle, 0), Err(StackError::NoRobots));
+        assert_eq!(operator_costs(1), Ok(exact(0)));
+        assert_eq!(operator_costs(2), Ok(exact(1)));
+    }
+
+    #[test]
+    fn test_complexity() {
+        for input in [EXAMPLE1, INPUT] {
+            let puzzle: Puzzle = input.parse().unwrap();
+            for n in [3, 26] {
+                let expected = puzzle.solve_with_costs(&robot_stack(n));
+                assert_eq!(complexity(&puzzle, n), Ok(expected.into()));
+            }
+        }
+    }
+}
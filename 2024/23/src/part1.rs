@@ -1,16 +1,9 @@
-use crate::puzzle::{Computer, Puzzle};
+use crate::puzzle::Puzzle;
 use itertools::Itertools as _;
-use pathfinding::prelude::maximal_cliques_collect;
-use std::collections::HashSet;
 
 pub fn run(input: &str) -> usize {
     let puzzle: Puzzle = input.parse().expect("parse failed");
-    let vertices: HashSet<Computer> = puzzle
-        .connections
-        .iter()
-        .flat_map(|&conn| vec![conn.a, conn.b])
-        .collect();
-    let cliques = maximal_cliques_collect(vertices, &mut |&a, &b| puzzle.connected(a, b));
+    let cliques: Vec<_> = puzzle.maximal_cliques().collect();
     let triangles = cliques
         .iter()
         .flat_map(|clique| clique.iter().sorted().combinations(3))
@@ -1,5 +1,5 @@
+use graph::UnGraph;
 use itertools::Itertools as _;
-use std::collections::HashSet;
 use std::fmt::{Debug, Display, Write};
 use std::str::FromStr;
 
@@ -35,45 +35,9 @@ impl Debug for Computer {
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-pub struct Connection {
-    pub a: Computer,
-    pub b: Computer,
-}
-
-#[derive(Debug)]
-pub struct ParseConnectionError;
-
-impl From<(Computer, Computer)> for Connection {
-    fn from((a, b): (Computer, Computer)) -> Self {
-        if a > b {
-            Connection { a: b, b: a }
-        } else {
-            Connection { a, b }
-        }
-    }
-}
-
-impl FromStr for Connection {
-    type Err = ParseConnectionError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (a, b) = s.split_once('-').ok_or(ParseConnectionError)?;
-        let a = a.parse().map_err(|_| ParseConnectionError)?;
-        let b = b.parse().map_err(|_| ParseConnectionError)?;
-        Ok((a, b).into())
-    }
-}
-
-impl Display for Connection {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}-{}", self.a, self.b)
-    }
-}
-
 #[derive(Debug)]
 pub struct Puzzle {
-    pub connections: HashSet<Connection>,
+    pub network: UnGraph<Computer>,
 }
 
 #[derive(Debug)]
@@ -84,17 +48,16 @@ impl FromStr for Puzzle {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(Puzzle {
-            connections: s
-                .lines()
-                .map(|s| s.parse())
-                .collect::<Result<_, _>>()
-                .map_err(|_| ParsePuzzleError)?,
+            network: s.parse().map_err(|_| ParsePuzzleError)?,
         })
     }
 }
 
 impl Puzzle {
-    pub fn connected(&self, a: Computer, b: Computer) -> bool {
-        self.connections.contains(&(a, b).into())
+    pub fn maximal_cliques(&self) -> impl Iterator<Item = Vec<Computer>> {
+        self.network
+            .maximal_cliques()
+            .into_iter()
+            .map(|clique| clique.into_iter().map(|v| *self.network.name(v)).collect())
     }
 }
@@ -0,0 +1,159 @@
+use crate::puzzle::Op;
+use std::collections::HashMap;
+
+// A node in a reduced ordered binary decision diagram. Equal functions
+// built in the same `Bdd` are always the same node.
+pub type Node = u32;
+
+pub const FALSE: Node = 0;
+pub const TRUE: Node = 1;
+
+const TERMINAL: u32 = u32::MAX;
+
+#[derive(Debug, Clone)]
+pub struct Bdd {
+    // (variable, low child, high child), with the terminals first.
+    nodes: Vec<(u32, Node, Node)>,
+    unique: HashMap<(u32, Node, Node), Node>,
+    cache: HashMap<(Op, Node, Node), Node>,
+}
+
+impl Default for Bdd {
+    fn default() -> Self {
+        Self {
+            nodes: vec![(TERMINAL, FALSE, FALSE), (TERMINAL, TRUE, TRUE)],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl Bdd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn make(&mut self, var: u32, low: Node, high: Node) -> Node {
+        if low == high {
+            return low;
+        }
+        if let Some(&node) = self.unique.get(&(var, low, high)) {
+            return node;
+        }
+        let node = self.nodes.len() as Node;
+        self.nodes.push((var, low, high));
+        self.unique.insert((var, low, high), node);
+        node
+    }
+
+    // The function that is true exactly when `var` is. Variables are
+    // tested in increasing order from the root.
+    pub fn var(&mut self, var: u32) -> Node {
+        self.make(var, FALSE, TRUE)
+    }
+
+    pub fn apply(&mut self, op: Op, a: Node, b: Node) -> Node {
+        let (a, b) = (a.min(b), a.max(b));
+        match (op, a, b) {
+            (Op::And, FALSE, _) | (Op::Or, TRUE, _) => return a,
+            (Op::And, TRUE, _) | (Op::Or | Op::Xor, FALSE, _) => return b,
+            (Op::And | Op::Or, a, b) if a == b => return a,
+            (Op::Xor, a, b) if a == b => return FALSE,
+            _ => {}
+        }
+        if let Some(&node) = self.cache.get(&(op, a, b)) {
+            return node;
+        }
+        let (var_a, low_a, high_a) = self.nodes[a as usize];
+        let (var_b, low_b, high_b) = self.nodes[b as usize];
+        let var = var_a.min(var_b);
+        let (low_a, high_a) = if var_a == var {
+            (low_a, high_a)
+        } else {
+            (a, a)
+        };
+        let (low_b, high_b) = if var_b == var {
+            (low_b, high_b)
+        } else {
+            (b, b)
+        };
+        let low = self.apply(op, low_a, low_b);
+        let high = self.apply(op, high_a, high_b);
+        let node = self.make(var, low, high);
+        self.cache.insert((op, a, b), node);
+        node
+    }
+
+    // Some assignment that makes the function true, as the variables on
+    // one path to the true terminal. Variables not listed don't matter.
+    pub fn satisfy(&self, mut node: Node) -> Option<Vec<(u32, bool)>> {
+        if node == FALSE {
+            return None;
+        }
+        let mut path = Vec::new();
+        while node > TRUE {
+            let (var, low, high) = self.nodes[node as usize];
+            let value = low == FALSE;
+            path.push((var, value));
+            node = if value { high } else { low };
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn eval(bdd: &Bdd, mut node: Node, assignment: impl Fn(u32) -> bool) -> bool {
+        while node > TRUE {
+            let (var, low, high) = bdd.nodes[node as usize];
+            node = if assignment(var) { high } else { low };
+        }
+        node == TRUE
+    }
+
+    // The number of decision nodes reachable from `node`.
+    pub fn size(bdd: &Bdd, node: Node) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if node > TRUE && seen.insert(node) {
+                let (_, low, high) = bdd.nodes[node as usize];
+                stack.extend([low, high]);
+            }
+        }
+        seen.len()
+    }
+
+    #[test]
+    fn test_canonical() {
+        let mut bdd = Bdd::new();
+        let (a, b) = (bdd.var(0), bdd.var(1));
+        let left = bdd.apply(Op::And, a, b);
+        let left = bdd.apply(Op::Xor, left, TRUE);
+        let not_a = bdd.apply(Op::Xor, a, TRUE);
+        let not_b = bdd.apply(Op::Xor, b, TRUE);
+        let right = bdd.apply(Op::Or, not_a, not_b);
+        assert_eq!(left, right);
+        assert_eq!(bdd.apply(Op::Xor, a, a), FALSE);
+        assert_eq!(bdd.apply(Op::Or, a, not_a), TRUE);
+    }
+
+    #[test]
+    fn test_eval_and_satisfy() {
+        let mut bdd = Bdd::new();
+        let (a, b, c) = (bdd.var(0), bdd.var(1), bdd.var(2));
+        let ab = bdd.apply(Op::And, a, b);
+        let f = bdd.apply(Op::Xor, ab, c);
+        for bits in 0..8 {
+            let value = |var: u32| bits >> var & 1 == 1;
+            assert_eq!(eval(&bdd, f, value), (value(0) && value(1)) != value(2));
+        }
+        let path = bdd.satisfy(f).unwrap();
+        assert!(eval(&bdd, f, |var| path.contains(&(var, true))));
+        assert_eq!(bdd.satisfy(FALSE), None);
+        assert_eq!(size(&bdd, f), 4);
+    }
+}
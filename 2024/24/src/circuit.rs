@@ -0,0 +1,240 @@
+use crate::puzzle::{FixedWire, Gate, Op, Puzzle, Wire, WireState};
+use graph::DiGraph;
+use parse_display::Display;
+use parse_display_with::formats::delimiter;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum CircuitError {
+    #[display("combinational loop through {0}")]
+    Loop(#[display(with = delimiter(", "))] Vec<Wire>),
+    #[display("wire {0} has more than one driver")]
+    MultipleDrivers(Wire),
+}
+
+// A netlist compiled for repeated evaluation. Wires are numbered and the
+// gates are kept in topological order, so one pass settles every wire.
+#[derive(Debug, Clone)]
+pub struct Circuit {
+    index: HashMap<Wire, usize>,
+    gates: Vec<Gate>,
+    compiled: Vec<(usize, Op, usize, usize)>,
+    values: Vec<WireState>,
+    // The numbered wires by prefix, as (bit, wire id).
+    buses: HashMap<char, Vec<(u8, usize)>>,
+}
+
+impl Circuit {
+    pub fn new(puzzle: &Puzzle) -> Result<Self, CircuitError> {
+        Self::from_parts(&puzzle.fixed_wires, &puzzle.gates)
+    }
+
+    pub fn from_parts(fixed_wires: &[FixedWire], gates: &[Gate]) -> Result<Self, CircuitError> {
+        let mut graph = DiGraph::new();
+        for fixed in fixed_wires {
+            graph.add_node(fixed.wire);
+        }
+        let mut drivers: HashMap<Wire, Gate> = HashMap::new();
+        for &gate in gates {
+            if drivers.insert(gate.output, gate).is_some() {
+                return Err(CircuitError::MultipleDrivers(gate.output));
+            }
+            graph.add_edge(gate.a, gate.output);
+            graph.add_edge(gate.b, gate.output);
+        }
+        let order = graph.topological_sort().map_err(|_| {
+            let component = graph
+                .strongly_connected_components()
+                .into_iter()
+                .find(|component| {
+                    component.len() > 1 || graph.successors(component[0]).contains(&component[0])
+                })
+                .expect("a cycle without a strongly connected component");
+            let mut wires: Vec<Wire> = component.into_iter().map(|id| *graph.name(id)).collect();
+            wires.sort();
+            CircuitError::Loop(wires)
+        })?;
+        let wires = graph.nodes().names().to_vec();
+        let index = (wires.iter().enumerate())
+            .map(|(id, &wire)| (wire, id))
+            .collect();
+        let gates: Vec<Gate> = (order.into_iter())
+            .filter_map(|id| drivers.get(graph.name(id)).copied())
+            .collect();
+        let mut buses: HashMap<char, Vec<(u8, usize)>> = HashMap::new();
+        for (id, wire) in wires.iter().enumerate() {
+            let prefix = wire.name[0].as_char();
+            if let Some(bit) = wire.bit(prefix) {
+                buses.entry(prefix).or_default().push((bit, id));
+            }
+        }
+        let mut circuit = Self {
+            values: vec![WireState::Off; wires.len()],
+            buses,
+            index,
+            compiled: Vec::new(),
+            gates,
+        };
+        circuit.compiled = (circuit.gates.iter())
+            .map(|gate| {
+                let id = |wire| circuit.index[&wire];
+                (id(gate.a), gate.op, id(gate.b), id(gate.output))
+            })
+            .collect();
+        for &FixedWire { wire, state } in fixed_wires {
+            circuit.set(wire, state);
+        }
+        Ok(circuit)
+    }
+
+    // The gates in evaluation order.
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    pub fn set(&mut self, wire: Wire, state: WireState) {
+        if let Some(&id) = self.index.get(&wire) {
+            self.values[id] = state;
+        }
+    }
+
+    // Settle every gate output from the current inputs.
+    pub fn evaluate(&mut self) {
+        for &(a, op, b, output) in &self.compiled {
+            self.values[output] = op.apply(self.values[a], self.values[b]);
+        }
+    }
+
+    fn bus_wires(&self, prefix: char) -> &[(u8, usize)] {
+        self.buses.get(&prefix).map_or(&[], Vec::as_slice)
+    }
+
+    // The number of bits on a bus, one more than its highest wire number.
+    pub fn width(&self, prefix: char) -> u8 {
+        (self.bus_wires(prefix).iter())
+            .map(|&(bit, _)| bit + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Read the wires `{prefix}00`, `{prefix}01`, ... as a little-endian
+    // integer. Missing bits read as 0.
+    pub fn bus(&self, prefix: char) -> u128 {
+        (self.bus_wires(prefix).iter())
+            .filter(|&&(_, id)| self.values[id] == WireState::On)
+            .fold(0, |acc, &(bit, _)| acc | 1 << bit)
+    }
+
+    // Drive the wires of a bus from an integer. Bits beyond the bus width
+    // are dropped.
+    pub fn set_bus(&mut self, prefix: char, value: u128) {
+        let Some(wires) = self.buses.get(&prefix) else {
+            return;
+        };
+        for &(bit, id) in wires {
+            self.values[id] = if value >> bit & 1 == 1 {
+                WireState::On
+            } else {
+                WireState::Off
+            };
+        }
+    }
+
+    // Feed x and y through the circuit and read z.
+    pub fn add(&mut self, x: u128, y: u128) -> u128 {
+        self.set_bus('x', x);
+        self.set_bus('y', y);
+        self.evaluate();
+        self.bus('z')
+    }
+}
+
+// The textbook adder: a half adder for bit 0 and a five-gate full adder
+// for each later bit, with internal wires named `sNN`, `aNN`, `bNN` and `cNN`.
+pub fn ripple_carry_adder(bits: u8) -> Vec<Gate> {
+    let wire = |prefix: char, bit: u8| Wire::from_str(&format!("{prefix}{bit:02}")).unwrap();
+    let carry = |bit: u8| match bit + 1 {
+        top if top == bits => Wire::z(top),
+        _ => wire('c', bit),
+    };
+    let mut gates = vec![
+        Gate::new(Wire::x(0), Op::Xor, Wire::y(0), Wire::z(0)),
+        Gate::new(Wire::x(0), Op::And, Wire::y(0), carry(0)),
+    ];
+    for bit in 1..bits {
+        let (x, y, z) = (Wire::x(bit), Wire::y(bit), Wire::z(bit));
+        let (s, a, b) = (wire('s', bit), wire('a', bit), wire('b', bit));
+        gates.extend([
+            Gate::new(x, Op::Xor, y, s),
+            Gate::new(x, Op::And, y, a),
+            Gate::new(s, Op::Xor, carry(bit - 1), z),
+            Gate::new(s, Op::And, carry(bit - 1), b),
+            Gate::new(a, Op::Or, b, carry(bit)),
+        ]);
+    }
+    gates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{gate, wire};
+
+    const EXAMPLE2: &'static str = include_str!("../data/example2");
+
+    #[test]
+    fn test_example() {
+        let puzzle: Puzzle = EXAMPLE2.parse().unwrap();
+        let mut circuit = Circuit::new(&puzzle).unwrap();
+        circuit.evaluate();
+        assert_eq!(circuit.bus('z'), 2024);
+        assert_eq!(circuit.width('x'), 5);
+        assert_eq!(circuit.bus('x'), 0b01101);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut circuit = Circuit::from_parts(&[], &ripple_carry_adder(8)).unwrap();
+        assert_eq!(circuit.width('z'), 9);
+        for (x, y) in [(0, 0), (1, 1), (200, 100), (255, 255), (0b1010, 0b0101)] {
+            assert_eq!(circuit.add(x, y), x + y);
+        }
+    }
+
+    #[test]
+    fn test_set_bus() {
+        let mut circuit = Circuit::from_parts(&[], &ripple_carry_adder(4)).unwrap();
+        circuit.set_bus('y', 0b1_0110);
+        assert_eq!(circuit.bus('y'), 0b0110);
+        assert_eq!(circuit.bus('s'), 0);
+        circuit.evaluate();
+        assert_eq!(circuit.bus('s'), 0b0110);
+    }
+
+    #[test]
+    fn test_loop() {
+        let gates = [
+            gate!(x00 AND abc -> def),
+            gate!(def OR y00 -> abc),
+            gate!(abc XOR x00 -> z00),
+        ];
+        assert_eq!(
+            Circuit::from_parts(&[], &gates).unwrap_err(),
+            CircuitError::Loop(vec![wire!(abc), wire!(def)])
+        );
+        assert_eq!(
+            CircuitError::Loop(vec![wire!(abc), wire!(def)]).to_string(),
+            "combinational loop through abc, def"
+        );
+    }
+
+    #[test]
+    fn test_multiple_drivers() {
+        let gates = [gate!(x00 AND y00 -> z00), gate!(x00 OR y00 -> z00)];
+        assert_eq!(
+            Circuit::from_parts(&[], &gates).unwrap_err(),
+            CircuitError::MultipleDrivers(wire!(z00))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::bdd::{self, Bdd, Node};
+use crate::circuit::{Circuit, CircuitError};
+use crate::puzzle::{Op, Puzzle, Wire, WireState};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+
+// An input assignment under which two wires disagree. Inputs that are not
+// listed don't matter and read as 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub wire: Wire,
+    pub inputs: Vec<(Wire, WireState)>,
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} differs", self.wire)?;
+        for (i, (wire, state)) in self.inputs.iter().enumerate() {
+            let sep = if i == 0 { " when " } else { ", " };
+            write!(f, "{sep}{wire}={state}")?;
+        }
+        Ok(())
+    }
+}
+
+// Every wire of one or more netlists as a BDD over their shared inputs.
+// Bus inputs are ordered by bit first, so x00, y00, x01, y01, ... which
+// keeps adders linear in size.
+#[derive(Debug)]
+pub struct Functions {
+    bdd: Bdd,
+    inputs: Vec<Wire>,
+    pub wires: Vec<HashMap<Wire, Node>>,
+}
+
+// Wires that no gate drives.
+fn undriven(puzzle: &Puzzle) -> impl Iterator<Item = Wire> + '_ {
+    let driven: BTreeSet<Wire> = puzzle.gates.iter().map(|gate| gate.output).collect();
+    (puzzle.gates.iter())
+        .flat_map(|gate| [gate.a, gate.b])
+        .chain(puzzle.fixed_wires.iter().map(|fixed| fixed.wire))
+        .filter(move |wire| !driven.contains(wire))
+}
+
+fn order(wire: &Wire) -> (u8, Wire) {
+    (wire.bit(wire.name[0].as_char()).unwrap_or(u8::MAX), *wire)
+}
+
+impl Functions {
+    pub fn new(puzzles: &[&Puzzle]) -> Result<Self, CircuitError> {
+        let mut inputs: Vec<Wire> = (puzzles.iter())
+            .flat_map(|puzzle| undriven(puzzle))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        inputs.sort_by_key(order);
+        let mut bdd = Bdd::new();
+        let vars: HashMap<Wire, Node> = (inputs.iter().enumerate())
+            .map(|(var, &wire)| (wire, bdd.var(var as u32)))
+            .collect();
+        let mut wires = Vec::new();
+        for puzzle in puzzles {
+            let circuit = Circuit::new(puzzle)?;
+            let mut functions = vars.clone();
+            for gate in circuit.gates() {
+                let node = bdd.apply(gate.op, functions[&gate.a], functions[&gate.b]);
+                functions.insert(gate.output, node);
+            }
+            wires.push(functions);
+        }
+        Ok(Self { bdd, inputs, wires })
+    }
+
+    // The assignment that tells the two functions apart, if any.
+    pub fn differ(&mut self, wire: Wire, a: Node, b: Node) -> Option<Counterexample> {
+        let difference = self.bdd.apply(Op::Xor, a, b);
+        let path = self.bdd.satisfy(difference)?;
+        let inputs = (path.into_iter())
+            .filter(|&(_, value)| value)
+            .map(|(var, _)| (self.inputs[var as usize], WireState::On))
+            .collect();
+        Some(Counterexample { wire, inputs })
+    }
+}
+
+// Check that two wires of a netlist compute the same function.
+pub fn check_wires(
+    puzzle: &Puzzle,
+    a: Wire,
+    b: Wire,
+) -> Result<Option<Counterexample>, CircuitError> {
+    let mut functions = Functions::new(&[puzzle])?;
+    let get = |wire| functions.wires[0].get(&wire).copied().unwrap_or(bdd::FALSE);
+    let (a_node, b_node) = (get(a), get(b));
+    Ok(functions.differ(a, a_node, b_node))
+}
+
+// Check that two netlists drive the same z outputs for every input. A z
+// wire missing from one side reads as 0 there.
+pub fn check_netlists(
+    left: &Puzzle,
+    right: &Puzzle,
+) -> Result<Option<Counterexample>, CircuitError> {
+    let mut functions = Functions::new(&[left, right])?;
+    let outputs: BTreeSet<Wire> = (left.gates.iter().chain(&right.gates))
+        .map(|gate| gate.output)
+        .filter(|wire| wire.bit('z').is_some())
+        .collect();
+    for wire in outputs {
+        let get = |side: usize| {
+            (functions.wires[side].get(&wire))
+                .copied()
+                .unwrap_or(bdd::FALSE)
+        };
+        let (a, b) = (get(0), get(1));
+        if let Some(counterexample) = functions.differ(wire, a, b) {
+            return Ok(Some(counterexample));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::circuit::ripple_carry_adder;
+    use crate::puzzle::Gate;
+    use crate::wire;
+
+    fn puzzle(gates: Vec<Gate>) -> Puzzle {
+        Puzzle {
+            fixed_wires: Vec::new(),
+            gates,
+        }
+    }
+
+    // A ripple adder that computes each carry as majority(x, y, carry).
+    pub fn majority_adder(bits: u8) -> Puzzle {
+        let wire = |prefix: char, bit: u8| format!("{prefix}{bit:02}").parse::<Wire>().unwrap();
+        let carry = |bit: u8| match bit + 1 {
+            top if top == bits => Wire::z(top),
+            _ => wire('c', bit),
+        };
+        let mut gates = vec![
+            Gate::new(Wire::x(0), Op::Xor, Wire::y(0), Wire::z(0)),
+            Gate::new(Wire::x(0), Op::And, Wire::y(0), carry(0)),
+        ];
+        for bit in 1..bits {
+            let (x, y, c) = (Wire::x(bit), Wire::y(bit), carry(bit - 1));
+            let [t, p, q, r, o] = ['t', 'p', 'q', 'r', 'o'].map(|prefix| wire(prefix, bit));
+            gates.extend([
+                Gate::new(y, Op::Xor, c, t),
+                Gate::new(x, Op::Xor, t, Wire::z(bit)),
+                Gate::new(x, Op::And, y, p),
+                Gate::new(x, Op::And, c, q),
+                Gate::new(y, Op::And, c, r),
+                Gate::new(p, Op::Or, q, o),
+                Gate::new(o, Op::Or, r, carry(bit)),
+            ]);
+        }
+        puzzle(gates)
+    }
+
+    #[test]
+    fn test_equivalent_architectures() {
+        let ripple = puzzle(ripple_carry_adder(40));
+        let majority = majority_adder(40);
+        assert_eq!(check_netlists(&ripple, &majority), Ok(None));
+        let functions = Functions::new(&[&ripple]).unwrap();
+        let z39 = functions.wires[0][&Wire::z(39)];
+        assert!(crate::bdd::test::size(&functions.bdd, z39) < 40 * 10);
+    }
+
+    #[test]
+    fn test_counterexample() {
+        let ripple = puzzle(ripple_carry_adder(8));
+        let mut broken = majority_adder(8);
+        broken.swap_outputs(wire!(q03), wire!(t03)).unwrap();
+        let counterexample = check_netlists(&ripple, &broken).unwrap().unwrap();
+        assert_eq!(counterexample.wire, Wire::z(3));
+
+        // Simulating the assignment shows the two netlists disagree there.
+        let results: Vec<u128> = [ripple, broken]
+            .iter()
+            .map(|puzzle| {
+                let mut circuit = Circuit::new(puzzle).unwrap();
+                for &(wire, state) in &counterexample.inputs {
+                    circuit.set(wire, state);
+                }
+                circuit.evaluate();
+                circuit.bus('z')
+            })
+            .collect();
+        assert_ne!(results[0] >> 3 & 1, results[1] >> 3 & 1);
+    }
+
+    #[test]
+    fn test_check_wires() {
+        let puzzle: Puzzle = "x00: 1\ny00: 0\n\n\
+            x00 XOR y00 -> abc\n\
+            x00 OR y00 -> def\n\
+            x00 AND y00 -> ghi\n\
+            def XOR ghi -> jkl"
+            .parse()
+            .unwrap();
+        assert_eq!(check_wires(&puzzle, wire!(abc), wire!(jkl)), Ok(None));
+        let counterexample = check_wires(&puzzle, wire!(abc), wire!(def))
+            .unwrap()
+            .unwrap();
+        assert_eq!(counterexample.to_string(), "abc differs when x00=1, y00=1");
+    }
+}
@@ -0,0 +1,272 @@
+use crate::puzzle::{Op, Puzzle, Wire};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+
+// A group of wires drawn together, such as the internals of one adder.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub label: String,
+    pub wires: Vec<Wire>,
+}
+
+// Extra structure to show in a DOT rendering.
+#[derive(Debug, Clone, Default)]
+pub struct Highlights {
+    pub clusters: Vec<Cluster>,
+    pub swapped: HashSet<Wire>,
+}
+
+fn color(op: Op) -> &'static str {
+    match op {
+        Op::And => "lightblue",
+        Op::Or => "palegreen",
+        Op::Xor => "lightsalmon",
+    }
+}
+
+// A wire on one of the x, y or z buses, which are ranked rather than
+// clustered.
+fn on_bus(wire: &Wire) -> bool {
+    ['x', 'y', 'z']
+        .iter()
+        .any(|&prefix| wire.bit(prefix).is_some())
+}
+
+// Render the netlist as a Graphviz digraph. Each gate is drawn as the node
+// for its output wire, coloured by its op; the input buses share the top
+// rank and the z bus the bottom one.
+pub fn dot(puzzle: &Puzzle, highlights: &Highlights) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph circuit {{").unwrap();
+    writeln!(out, "  node [shape=box, style=filled, fillcolor=white];").unwrap();
+    let inputs: BTreeSet<Wire> = puzzle.fixed_wires.iter().map(|fixed| fixed.wire).collect();
+    let outputs: BTreeSet<Wire> = (puzzle.gates.iter())
+        .map(|gate| gate.output)
+        .filter(|wire| wire.bit('z').is_some())
+        .collect();
+    for (rank, wires) in [("min", &inputs), ("max", &outputs)] {
+        let names: Vec<String> = wires.iter().map(Wire::to_string).collect();
+        writeln!(out, "  {{ rank={rank}; {}; }}", names.join("; ")).unwrap();
+    }
+    for (i, cluster) in highlights.clusters.iter().enumerate() {
+        let names: Vec<String> = (cluster.wires.iter())
+            .filter(|wire| !on_bus(wire))
+            .map(Wire::to_string)
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        writeln!(out, "  subgraph cluster_{i} {{").unwrap();
+        writeln!(out, "    label=\"{}\";", cluster.label).unwrap();
+        writeln!(out, "    {};", names.join("; ")).unwrap();
+        writeln!(out, "  }}").unwrap();
+    }
+    for wire in &inputs {
+        writeln!(out, "  {wire} [shape=ellipse];").unwrap();
+    }
+    for gate in &puzzle.gates {
+        let output = gate.output;
+        write!(
+            out,
+            "  {output} [label=\"{output}\\n{}\", fillcolor={}",
+            gate.op,
+            color(gate.op)
+        )
+        .unwrap();
+        if highlights.swapped.contains(&output) {
+            write!(out, ", color=red, penwidth=3").unwrap();
+        }
+        writeln!(out, "];").unwrap();
+        writeln!(out, "  {} -> {output};", gate.a).unwrap();
+        writeln!(out, "  {} -> {output};", gate.b).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "buf", "end", "for", "int", "nor", "not", "reg", "tri", "use", "wor", "xor",
+];
+
+// The bus and bit of a wire on the x or y input or the z output bus. Only
+// undriven x and y wires and driven z wires are on a bus; any other wire
+// with those names is a scalar.
+fn bus_bit(wire: &Wire, driven: &HashSet<Wire>) -> Option<(char, u8)> {
+    ['x', 'y', 'z']
+        .into_iter()
+        .filter(|&prefix| (prefix == 'z') == driven.contains(wire))
+        .find_map(|prefix| Some((prefix, wire.bit(prefix)?)))
+}
+
+// The Verilog name for a wire: bus wires become bit selects, and scalars
+// that collide with a keyword or look like a bus bit are escaped.
+fn verilog_name(wire: &Wire, driven: &HashSet<Wire>) -> String {
+    if let Some((prefix, bit)) = bus_bit(wire, driven) {
+        return format!("{prefix}[{bit}]");
+    }
+    let name = wire.to_string();
+    if KEYWORDS.contains(&name.as_str()) || on_bus(wire) {
+        format!("\\{name} ")
+    } else {
+        name
+    }
+}
+
+// Render the netlist as a structural Verilog module. Undriven wires become
+// inputs and the z bus the output; the x, y and z buses become vectors.
+pub fn verilog(puzzle: &Puzzle, module: &str) -> String {
+    let driven: HashSet<Wire> = puzzle.gates.iter().map(|gate| gate.output).collect();
+    let mut buses: BTreeMap<char, u8> = BTreeMap::new();
+    let mut inputs: BTreeSet<Wire> = BTreeSet::new();
+    let mut internal: BTreeSet<Wire> = BTreeSet::new();
+    let wires = (puzzle.fixed_wires.iter().map(|fixed| fixed.wire)).chain(
+        puzzle
+            .gates
+            .iter()
+            .flat_map(|gate| [gate.a, gate.b, gate.output]),
+    );
+    for wire in wires {
+        if let Some((prefix, bit)) = bus_bit(&wire, &driven) {
+            let width = buses.entry(prefix).or_default();
+            *width = (*width).max(bit + 1);
+        } else if driven.contains(&wire) {
+            internal.insert(wire);
+        } else {
+            inputs.insert(wire);
+        }
+    }
+    let mut ports: Vec<String> = Vec::new();
+    for (&prefix, &width) in &buses {
+        let direction = if prefix == 'z' { "output" } else { "input" };
+        ports.push(format!("{direction} [{}:0] {prefix}", width - 1));
+    }
+    for wire in &inputs {
+        ports.push(format!("input {}", verilog_name(wire, &driven)));
+    }
+    let mut out = String::new();
+    writeln!(out, "module {module} (").unwrap();
+    writeln!(out, "  {}", ports.join(",\n  ")).unwrap();
+    writeln!(out, ");").unwrap();
+    for wire in &internal {
+        writeln!(out, "  wire {};", verilog_name(wire, &driven)).unwrap();
+    }
+    for (i, gate) in puzzle.gates.iter().enumerate() {
+        let primitive = match gate.op {
+            Op::And => "and",
+            Op::Or => "or",
+            Op::Xor => "xor",
+        };
+        writeln!(
+            out,
+            "  {primitive} g{i} ({}, {}, {});",
+            verilog_name(&gate.output, &driven),
+            verilog_name(&gate.a, &driven),
+            verilog_name(&gate.b, &driven)
+        )
+        .unwrap();
+    }
+    writeln!(out, "endmodule").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire;
+
+    const EXAMPLE1: &'static str = include_str!("../data/example1");
+
+    fn half_adder() -> Puzzle {
+        "x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\nx00 AND y00 -> and"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dot() {
+        let puzzle: Puzzle = EXAMPLE1.parse().unwrap();
+        let dot = dot(&puzzle, &Highlights::default());
+        assert!(dot.starts_with("digraph circuit {\n"));
+        assert!(dot.contains("  { rank=min; x00; x01; x02; y00; y01; y02; }\n"));
+        assert!(dot.contains("  { rank=max; z00; z01; z02; }\n"));
+        assert!(dot.contains("  z01 [label=\"z01\\nXOR\", fillcolor=lightsalmon];\n"));
+        assert!(dot.contains("  y02 -> z02;\n"));
+        assert!(!dot.contains("cluster"));
+    }
+
+    #[test]
+    fn test_dot_highlights() {
+        let highlights = Highlights {
+            clusters: vec![Cluster {
+                label: "HalfAdder 0".to_string(),
+                wires: vec![wire!(z00), wire!(and)],
+            }],
+            swapped: [wire!(and)].into_iter().collect(),
+        };
+        let dot = dot(&half_adder(), &highlights);
+        assert!(
+            dot.contains("  subgraph cluster_0 {\n    label=\"HalfAdder 0\";\n    and;\n  }\n")
+        );
+        assert!(dot.contains(
+            "  and [label=\"and\\nAND\", fillcolor=lightblue, color=red, penwidth=3];\n"
+        ));
+    }
+
+    #[test]
+    fn test_verilog() {
+        assert_eq!(
+            verilog(&half_adder(), "half_adder"),
+            "module half_adder (\n  \
+               input [0:0] x,\n  \
+               input [0:0] y,\n  \
+               output [0:0] z\n\
+             );\n  \
+               wire \\and ;\n  \
+               xor g0 (z[0], x[0], y[0]);\n  \
+               and g1 (\\and , x[0], y[0]);\n\
+             endmodule\n"
+        );
+    }
+
+    #[test]
+    fn test_verilog_example() {
+        let puzzle: Puzzle = EXAMPLE1.parse().unwrap();
+        let verilog = verilog(&puzzle, "example");
+        assert!(verilog.contains("  input [2:0] x,\n  input [2:0] y,\n  output [2:0] z\n"));
+        assert!(verilog.contains("  or g2 (z[2], x[2], y[2]);\n"));
+    }
+
+    #[test]
+    fn test_verilog_internal_wires() {
+        let puzzle: Puzzle = "x00: 1\nx01: 0\ny00: 1\ny01: 0\n\n\
+                              x00 XOR y00 -> z00\nx00 AND y00 -> c00\n\
+                              x01 XOR y01 -> s01\ns01 XOR c00 -> z01"
+            .parse()
+            .unwrap();
+        let verilog = verilog(&puzzle, "adder");
+        assert!(verilog.contains("  wire c00;\n  wire s01;\n"));
+        assert!(verilog.contains("  xor g2 (s01, x[1], y[1]);\n"));
+        assert!(verilog.contains("  xor g3 (z[1], c00, s01);\n"));
+        assert!(!verilog.contains("s[") && !verilog.contains("c["));
+    }
+
+    #[test]
+    fn test_verilog_driven_input_name() {
+        let puzzle: Puzzle = "x00: 1\ny00: 0\n\nx00 XOR y00 -> x01\nx01 AND y00 -> z00"
+            .parse()
+            .unwrap();
+        let verilog = verilog(&puzzle, "m");
+        assert!(verilog.contains("  input [0:0] x,\n  input [0:0] y,\n  output [0:0] z\n"));
+        assert!(verilog.contains("  wire \\x01 ;\n"));
+        assert!(verilog.contains("  xor g0 (\\x01 , x[0], y[0]);\n"));
+        assert!(verilog.contains("  and g1 (z[0], \\x01 , y[0]);\n"));
+    }
+
+    #[test]
+    fn test_verilog_undriven_output_name() {
+        let puzzle: Puzzle = "x00: 1\nz01: 0\n\nx00 XOR z01 -> z00".parse().unwrap();
+        let verilog = verilog(&puzzle, "m");
+        assert!(verilog.contains("  input [0:0] x,\n  output [0:0] z,\n  input \\z01 \n);\n"));
+        assert!(verilog.contains("  xor g0 (z[0], x[0], \\z01 );\n"));
+    }
+}
@@ -1,9 +1,93 @@
+mod bdd;
+mod circuit;
+mod equivalence;
+mod export;
 mod part1;
 mod part2;
 mod puzzle;
+mod rewrite;
+mod verify;
 
 fn main() {
     const INPUT: &'static str = include_str!("../data/input");
-    println!("Part 1: {}", part1::run(INPUT));
-    println!("Part 2: {}", part2::run(INPUT));
+    match std::env::args().nth(1).as_deref() {
+        // Print the input circuit as a Graphviz digraph, clustered by adder.
+        Some("dot") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            let highlights = match part2::recognize(&puzzle) {
+                Ok(adders) => {
+                    if let Some((bit, err)) = &adders.error {
+                        eprintln!("Finding full adder for bit {bit}: {err}");
+                    }
+                    adders.highlights()
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    Default::default()
+                }
+            };
+            print!("{}", export::dot(&puzzle, &highlights));
+        }
+        // Print the input circuit as a structural Verilog module.
+        Some("verilog") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            print!("{}", export::verilog(&puzzle, "adder"));
+        }
+        // Print the input with the swaps found by part 2 undone and any gates
+        // that don't reach a z wire removed.
+        Some("fix") => {
+            let mut puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            match part2::find_swaps(&puzzle) {
+                Ok(swaps) => {
+                    puzzle
+                        .apply_swaps(&swaps)
+                        .expect("repair swapped an undriven wire");
+                    for gate in puzzle.remove_dead_gates() {
+                        eprintln!("removed dead gate {gate}");
+                    }
+                    println!("{puzzle}");
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        // Check whether two wires of the input compute the same function.
+        Some("equiv") => {
+            let puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            let wire = |n| -> puzzle::Wire {
+                let name = std::env::args().nth(n).expect("usage: equiv <a> <b>");
+                name.parse().expect("parse failed")
+            };
+            match equivalence::check_wires(&puzzle, wire(2), wire(3)) {
+                Ok(None) => println!("equivalent"),
+                Ok(Some(counterexample)) => println!("{counterexample}"),
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        // Print the input with a wire renamed, e.g. `rename abc carry`.
+        Some("rename") => {
+            let mut puzzle: puzzle::Puzzle = INPUT.parse().expect("parse failed");
+            let wire = |n| -> puzzle::Wire {
+                let name = std::env::args().nth(n).expect("usage: rename <from> <to>");
+                name.parse().expect("parse failed")
+            };
+            if let Err(err) = puzzle.rename(wire(2), wire(3)) {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+            println!("{puzzle}");
+        }
+        _ => {
+            println!("Part 1: {}", part1::run(INPUT));
+            match part2::run(INPUT) {
+                Ok(swaps) => println!("Part 2: {swaps}"),
+                Err(err) => println!("Part 2: {err}"),
+            }
+        }
+    }
 }
@@ -181,31 +181,25 @@ impl Gates {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::puzzle::Wire;
+    use crate::puzzle::{Puzzle, Wire};
     use crate::{gate, wire};
 
-    fn swap(target: &mut Wire, (a, b): (Wire, Wire)) {
-        if target == &a {
-            *target = b
-        } else if target == &b {
-            *target = a
-        }
-    }
-
     fn make_full_adder(swapping: Option<(Wire, Wire)>) -> Vec<Gate> {
-        let mut gates = vec![
+        let gates = vec![
             gate!(ina XOR inb -> sma),
             gate!(ina AND inb -> cca),
             gate!(sma XOR cci -> smo),
             gate!(sma AND cci -> ccb),
             gate!(cca OR ccb -> cco),
         ];
+        let mut puzzle = Puzzle {
+            fixed_wires: Vec::new(),
+            gates,
+        };
         if let Some((a, b)) = swapping {
-            for gate in &mut gates {
-                swap(&mut gate.output, (a, b));
-            }
+            puzzle.swap_outputs(a, b).unwrap();
         }
-        gates
+        puzzle.gates
     }
 
     #[test]
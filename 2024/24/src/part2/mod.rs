@@ -1,45 +1,151 @@
+use crate::export::{Cluster, Highlights};
 use crate::puzzle::{Puzzle, Wire};
-use gates::FullAdderNotFoundError;
+use crate::verify::{self, RepairError};
+use gates::{AdderOutput, FullAdder, FullAdderNotFoundError, GateNotFoundError, HalfAdder};
 
 mod gates;
 
-pub fn run(input: &str) -> impl std::fmt::Display {
-    let puzzle: Puzzle = input.parse().expect("parse failed");
-    let gates: gates::Gates = puzzle.gates.iter().collect();
+// The adders recognized along the carry chain, the swapped outputs found on
+// the way, and the bit that stopped the walk, if any.
+#[derive(Debug)]
+pub struct Adders {
+    pub half_adder: HalfAdder,
+    pub full_adders: Vec<FullAdder>,
+    pub swaps: Vec<Wire>,
+    pub error: Option<(u8, FullAdderNotFoundError)>,
+}
 
+pub fn recognize(puzzle: &Puzzle) -> Result<Adders, GateNotFoundError> {
+    let gates: gates::Gates = puzzle.gates.iter().collect();
     let bits = u8::try_from(puzzle.fixed_wires.len() / 2).unwrap(); // input bits
-    assert_eq!(puzzle.gates.len(), (bits * 5 - 3).into());
-
-    let mut swaps = Vec::<Wire>::new();
-    let half_adder_0 = gates
-        .find_half_adder_with_inputs((Wire::x(0), Wire::y(0)).into())
-        .unwrap();
-    assert_eq!(half_adder_0.output.sum, Wire::z(0));
-    let mut carry = half_adder_0.output.carry;
+
+    let half_adder = gates.find_half_adder_with_inputs((Wire::x(0), Wire::y(0)))?;
+    let mut adders = Adders {
+        half_adder,
+        full_adders: Vec::new(),
+        swaps: Vec::new(),
+        error: None,
+    };
+    let mut carry = half_adder.output.carry;
     for bit in 1..bits {
         let full_adder = gates.find_full_adder_for_bit_and_input_carry(bit, carry);
         match full_adder {
             Ok(full_adder) => {
                 carry = full_adder.output_carry;
+                adders.full_adders.push(full_adder);
             }
             Err(FullAdderNotFoundError::OkWithSwap(full_adder, a, b)) => {
-                swaps.push(a);
-                swaps.push(b);
+                adders.swaps.push(a);
+                adders.swaps.push(b);
                 carry = full_adder.output_carry;
+                adders.full_adders.push(full_adder);
             }
             Err(err) => {
-                println!("Finding full adder for bit {bit}: {err}");
-                return "ERROR".into();
+                adders.error = Some((bit, err));
+                break;
             }
         }
     }
-    if carry != Wire::z(bits) {
-        println!("Final carry was {carry:?}, want {}", Wire::z(bits));
+    Ok(adders)
+}
+
+impl Adders {
+    // One cluster per adder, with the recognized swaps highlighted.
+    pub fn highlights(&self) -> Highlights {
+        let AdderOutput { sum, carry } = self.half_adder.output;
+        let mut clusters = vec![Cluster {
+            label: "HalfAdder 0".to_string(),
+            wires: vec![sum, carry],
+        }];
+        for (bit, full_adder) in (1..).zip(&self.full_adders) {
+            let (first, second) = full_adder.half_adders;
+            clusters.push(Cluster {
+                label: format!("FullAdder {bit}"),
+                wires: vec![
+                    first.output.sum,
+                    first.output.carry,
+                    second.output.sum,
+                    second.output.carry,
+                    full_adder.output_carry,
+                ],
+            });
+        }
+        Highlights {
+            clusters,
+            swapped: self.swaps.iter().copied().collect(),
+        }
     }
+}
+
+// The swaps that make the netlist add. A ripple carry adder is recognized
+// directly, which is fast; anything else falls back to the search in
+// `verify::repair`.
+pub fn find_swaps(puzzle: &Puzzle) -> Result<Vec<(Wire, Wire)>, RepairError> {
+    if let Ok(adders) = recognize(puzzle)
+        && adders.error.is_none()
+    {
+        let swaps: Vec<(Wire, Wire)> = (adders.swaps.chunks(2))
+            .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+            .collect();
+        let mut repaired = Puzzle {
+            fixed_wires: puzzle.fixed_wires.clone(),
+            gates: puzzle.gates.clone(),
+        };
+        if repaired.apply_swaps(&swaps).is_ok() && verify::adds(&repaired)? {
+            return Ok(swaps);
+        }
+    }
+    verify::repair(puzzle, 4)
+}
+
+// The swapped output wires, sorted and comma separated.
+pub fn run(input: &str) -> Result<String, RepairError> {
+    let puzzle: Puzzle = input.parse().expect("parse failed");
+    let mut swaps: Vec<Wire> = find_swaps(&puzzle)?
+        .into_iter()
+        .flat_map(|(a, b)| [a, b])
+        .collect();
     swaps.sort();
-    swaps
+    Ok(swaps
         .into_iter()
         .map(|wire| wire.to_string())
         .collect::<Vec<_>>()
-        .join(",")
+        .join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circuit::ripple_carry_adder;
+    use crate::equivalence::test::majority_adder;
+    use crate::puzzle::{FixedWire, Gate, WireState};
+    use crate::wire;
+
+    // An adder with its x and y inputs all off, as in a puzzle input.
+    fn puzzle(bits: u8, gates: Vec<Gate>, swaps: &[(Wire, Wire)]) -> Puzzle {
+        let fixed_wires = (0..bits)
+            .flat_map(|bit| [Wire::x(bit), Wire::y(bit)])
+            .map(|wire| FixedWire {
+                wire,
+                state: WireState::Off,
+            })
+            .collect();
+        let mut puzzle = Puzzle { fixed_wires, gates };
+        puzzle.apply_swaps(swaps).unwrap();
+        puzzle
+    }
+
+    #[test]
+    fn test_find_swaps() {
+        let swaps = [(wire!(a02), wire!(z02)), (wire!(b05), wire!(z05))];
+        let ripple = puzzle(8, ripple_carry_adder(8), &swaps);
+        assert!(recognize(&ripple).unwrap().error.is_none());
+        let mut found = find_swaps(&ripple).unwrap();
+        found.sort();
+        assert_eq!(found, swaps);
+        // Not a ripple carry adder, so found by the search instead.
+        let swaps = [(wire!(q03), wire!(t03))];
+        let found = find_swaps(&puzzle(6, majority_adder(6).gates, &swaps)).unwrap();
+        assert_eq!(found, swaps);
+    }
 }
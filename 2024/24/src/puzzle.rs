@@ -9,7 +9,7 @@ use std::str::FromStr;
 #[derive(Debug)]
 pub struct ParseError;
 
-#[derive(Debug, FromStr)]
+#[derive(Debug, Display, FromStr, PartialEq, Eq)]
 #[display("{fixed_wires}\n\n{gates}")]
 pub struct Puzzle {
     #[display(with=delimiter("\n"))]
@@ -18,7 +18,7 @@ pub struct Puzzle {
     pub gates: Vec<Gate>,
 }
 
-#[derive(Clone, Copy, Debug, Display, FromStr)]
+#[derive(Clone, Copy, Debug, Display, FromStr, PartialEq, Eq)]
 #[display("{wire}: {state}")]
 pub struct FixedWire {
     pub wire: Wire,
@@ -36,7 +36,7 @@ pub struct Gate {
 }
 
 impl Gate {
-    fn new(a: Wire, op: Op, b: Wire, output: Wire) -> Self {
+    pub fn new(a: Wire, op: Op, b: Wire, output: Wire) -> Self {
         let (a, b) = (a.min(b), a.max(b));
         Self { a, op, b, output }
     }
@@ -65,6 +65,14 @@ impl Wire {
         use ascii::AsciiChar::*;
         matches!(self.name[0], x | y)
     }
+    // The bit number if this wire is on the bus with the given prefix.
+    pub fn bit(&self, prefix: char) -> Option<u8> {
+        let [first, tens, ones] = self.name;
+        if first.as_char() != prefix || !tens.is_ascii_digit() || !ones.is_ascii_digit() {
+            return None;
+        }
+        Some((tens.as_byte() - b'0') * 10 + ones.as_byte() - b'0')
+    }
 }
 
 impl Display for Wire {
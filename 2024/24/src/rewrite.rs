@@ -0,0 +1,174 @@
+use crate::puzzle::{Gate, Puzzle, Wire};
+use parse_display::Display;
+use std::collections::HashSet;
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum RewriteError {
+    #[display("no gate drives {0}")]
+    NotDriven(Wire),
+    #[display("no wire named {0}")]
+    Unknown(Wire),
+    #[display("wire {0} is already in use")]
+    InUse(Wire),
+}
+
+impl Puzzle {
+    fn driver(&self, wire: Wire) -> Result<usize, RewriteError> {
+        (self.gates.iter())
+            .position(|gate| gate.output == wire)
+            .ok_or(RewriteError::NotDriven(wire))
+    }
+
+    fn wires(&self) -> impl Iterator<Item = Wire> + '_ {
+        (self.fixed_wires.iter().map(|fixed| fixed.wire)).chain(
+            self.gates
+                .iter()
+                .flat_map(|gate| [gate.a, gate.b, gate.output]),
+        )
+    }
+
+    // Exchange the output wires of the gates driving `a` and `b`.
+    pub fn swap_outputs(&mut self, a: Wire, b: Wire) -> Result<(), RewriteError> {
+        let (i, j) = (self.driver(a)?, self.driver(b)?);
+        self.gates[i].output = b;
+        self.gates[j].output = a;
+        Ok(())
+    }
+
+    // Give a wire a new name everywhere it appears.
+    pub fn rename(&mut self, from: Wire, to: Wire) -> Result<(), RewriteError> {
+        if self.wires().any(|wire| wire == to) {
+            return Err(RewriteError::InUse(to));
+        }
+        if !self.wires().any(|wire| wire == from) {
+            return Err(RewriteError::Unknown(from));
+        }
+        let rename = |wire: Wire| if wire == from { to } else { wire };
+        for fixed in &mut self.fixed_wires {
+            fixed.wire = rename(fixed.wire);
+        }
+        for gate in &mut self.gates {
+            (gate.a, gate.b, gate.output) = (rename(gate.a), rename(gate.b), rename(gate.output));
+        }
+        // The new name may sort differently against the other input.
+        self.canonicalize();
+        Ok(())
+    }
+
+    // Remove gates whose outputs reach no z wire, returning them.
+    pub fn remove_dead_gates(&mut self) -> Vec<Gate> {
+        let mut removed = Vec::new();
+        loop {
+            let used: HashSet<Wire> = (self.gates.iter())
+                .flat_map(|gate| [gate.a, gate.b])
+                .collect();
+            let (live, dead): (Vec<Gate>, Vec<Gate>) = (self.gates.iter())
+                .partition(|gate| gate.output.bit('z').is_some() || used.contains(&gate.output));
+            if dead.is_empty() {
+                return removed;
+            }
+            self.gates = live;
+            removed.extend(dead);
+        }
+    }
+
+    // Order the inputs of every gate the way parsing does.
+    pub fn canonicalize(&mut self) {
+        for gate in &mut self.gates {
+            *gate = Gate::new(gate.a, gate.op, gate.b, gate.output);
+        }
+    }
+
+    // Swap every pair of outputs in turn.
+    pub fn apply_swaps(&mut self, swaps: &[(Wire, Wire)]) -> Result<(), RewriteError> {
+        for &(a, b) in swaps {
+            self.swap_outputs(a, b)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::Op;
+    use crate::{gate, wire};
+
+    const EXAMPLE2: &'static str = include_str!("../data/example2");
+    const INPUT: &'static str = include_str!("../data/input");
+
+    #[test]
+    fn test_round_trip() {
+        for input in [EXAMPLE2, INPUT] {
+            let puzzle: Puzzle = input.parse().unwrap();
+            // Gate inputs come back in canonical order.
+            let printed = puzzle.to_string();
+            assert_eq!(printed.lines().count(), input.trim_end().lines().count());
+            assert_eq!(printed.parse::<Puzzle>().unwrap(), puzzle);
+        }
+    }
+
+    #[test]
+    fn test_swap_outputs() {
+        let mut puzzle: Puzzle = "x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\nx00 AND y00 -> z01"
+            .parse()
+            .unwrap();
+        puzzle.swap_outputs(wire!(z00), wire!(z01)).unwrap();
+        assert_eq!(
+            puzzle.to_string(),
+            "x00: 1\ny00: 0\n\nx00 XOR y00 -> z01\nx00 AND y00 -> z00"
+        );
+        assert_eq!(
+            puzzle.swap_outputs(wire!(z00), wire!(x00)),
+            Err(RewriteError::NotDriven(wire!(x00)))
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut puzzle: Puzzle = "x00: 1\ny00: 0\n\nx00 AND y00 -> abc\nabc OR x00 -> z00"
+            .parse()
+            .unwrap();
+        puzzle.rename(wire!(abc), wire!(aaa)).unwrap();
+        assert_eq!(
+            puzzle.gates,
+            [gate!(x00 AND y00 -> aaa), gate!(aaa OR x00 -> z00)]
+        );
+        puzzle.rename(wire!(y00), wire!(y01)).unwrap();
+        assert_eq!(puzzle.fixed_wires[1].to_string(), "y01: 0");
+        assert_eq!(
+            puzzle.rename(wire!(aaa), wire!(x00)),
+            Err(RewriteError::InUse(wire!(x00)))
+        );
+        assert_eq!(
+            puzzle.rename(wire!(qqq), wire!(rrr)),
+            Err(RewriteError::Unknown(wire!(qqq)))
+        );
+    }
+
+    #[test]
+    fn test_remove_dead_gates() {
+        let mut puzzle: Puzzle = "x00: 1\ny00: 0\n\n\
+            x00 AND y00 -> abc\n\
+            abc OR x00 -> def\n\
+            x00 XOR y00 -> z00"
+            .parse()
+            .unwrap();
+        let removed = puzzle.remove_dead_gates();
+        assert_eq!(
+            removed,
+            [gate!(abc OR x00 -> def), gate!(x00 AND y00 -> abc)]
+        );
+        assert_eq!(puzzle.gates, [gate!(x00 XOR y00 -> z00)]);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut puzzle = Puzzle {
+            fixed_wires: Vec::new(),
+            gates: vec![gate!(y00 AND x00 -> z00)],
+        };
+        puzzle.canonicalize();
+        assert_eq!(puzzle.gates, [gate!(x00 AND y00 -> z00)]);
+    }
+}
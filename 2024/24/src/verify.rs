@@ -0,0 +1,283 @@
+use crate::circuit::{Circuit, CircuitError, ripple_carry_adder};
+use crate::equivalence::{Counterexample, check_netlists};
+use crate::puzzle::{FixedWire, Gate, Puzzle, Wire};
+use parse_display::Display;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum RepairError {
+    #[display("{0}")]
+    Circuit(CircuitError),
+    #[display("no repair with at most {0} swaps")]
+    NoRepair(usize),
+}
+
+// Prove that a netlist adds, by comparing it with a ripple carry adder of
+// the same width.
+pub fn prove(puzzle: &Puzzle) -> Result<Option<Counterexample>, CircuitError> {
+    let bits = Circuit::new(puzzle)?.width('x');
+    let reference = Puzzle {
+        fixed_wires: Vec::new(),
+        gates: ripple_carry_adder(bits),
+    };
+    check_netlists(&reference, puzzle)
+}
+
+impl From<CircuitError> for RepairError {
+    fn from(value: CircuitError) -> Self {
+        RepairError::Circuit(value)
+    }
+}
+
+// SplitMix64, so the random vectors are the same on every run.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn bits(&mut self, bits: u8) -> u128 {
+        let value = (self.next() as u128) << 64 | self.next() as u128;
+        value & mask(bits)
+    }
+}
+
+fn mask(bits: u8) -> u128 {
+    1u128
+        .checked_shl(bits.into())
+        .map_or(u128::MAX, |bit| bit - 1)
+}
+
+// Inputs for an adder of the given width: the full adder truth table at
+// every bit, with the carry in made by the bit below, then `random` pairs
+// of random operands.
+pub fn test_vectors(bits: u8, random: usize) -> Vec<(u128, u128)> {
+    let mut vectors = vec![(0, 0)];
+    for bit in 0..bits {
+        for combo in 1..8u128 {
+            let (x, y, carry) = (combo & 1, combo >> 1 & 1, combo >> 2);
+            if carry == 1 && bit == 0 {
+                continue;
+            }
+            let carry = carry << bit >> 1;
+            vectors.push((x << bit | carry, y << bit | carry));
+        }
+    }
+    let mut rng = Random(u64::from(bits));
+    vectors.extend((0..random).map(|_| (rng.bits(bits), rng.bits(bits))));
+    vectors
+}
+
+// The z bits that came out wrong for any of the vectors.
+pub fn faulty_bits(circuit: &mut Circuit, vectors: &[(u128, u128)]) -> u128 {
+    let z = mask(circuit.width('z'));
+    (vectors.iter())
+        .map(|&(x, y)| (circuit.add(x, y) ^ (x + y)) & z)
+        .fold(0, |acc, wrong| acc | wrong)
+}
+
+// Whether the netlist gets every test vector right.
+pub fn adds(puzzle: &Puzzle) -> Result<bool, CircuitError> {
+    let mut circuit = Circuit::new(puzzle)?;
+    let vectors = test_vectors(circuit.width('x').max(circuit.width('y')), 64);
+    Ok(faulty_bits(&mut circuit, &vectors) == 0)
+}
+
+// Whether every z bit up to and including `bit` is right for all vectors.
+// The random vectors come last but catch most faults, so they go first.
+fn passes_through(circuit: &mut Circuit, vectors: &[(u128, u128)], bit: u32) -> bool {
+    let low = mask(bit as u8 + 1);
+    (vectors.iter().rev()).all(|&(x, y)| (circuit.add(x, y) ^ (x + y)) & low == 0)
+}
+
+// The gate outputs that a wire depends on, including its own.
+fn cone(drivers: &HashMap<Wire, Gate>, wire: Wire) -> HashSet<Wire> {
+    let mut cone = HashSet::new();
+    let mut stack = vec![wire];
+    while let Some(wire) = stack.pop() {
+        if let Some(gate) = drivers.get(&wire)
+            && cone.insert(wire)
+        {
+            stack.extend([gate.a, gate.b]);
+        }
+    }
+    cone
+}
+
+struct Search<'a> {
+    fixed_wires: &'a [FixedWire],
+    vectors: &'a [(u128, u128)],
+    gates: Vec<Gate>,
+    swaps: Vec<(Wire, Wire)>,
+}
+
+impl Search<'_> {
+    // Gates whose outputs could be to blame for a fault at `bit`: those that
+    // first appear in its cone, or the whole cone if there are none.
+    fn suspects(&self, bit: u8) -> Vec<usize> {
+        let drivers: HashMap<Wire, Gate> = (self.gates.iter())
+            .map(|&gate| (gate.output, gate))
+            .collect();
+        let lower: HashSet<Wire> = (0..bit)
+            .flat_map(|bit| cone(&drivers, Wire::z(bit)))
+            .collect();
+        let cone = cone(&drivers, Wire::z(bit));
+        let fresh: Vec<usize> = (0..self.gates.len())
+            .filter(|&i| cone.contains(&self.gates[i].output))
+            .filter(|&i| !lower.contains(&self.gates[i].output))
+            .collect();
+        if fresh.is_empty() {
+            (0..self.gates.len())
+                .filter(|&i| cone.contains(&self.gates[i].output))
+                .collect()
+        } else {
+            fresh
+        }
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        let output = self.gates[i].output;
+        self.gates[i].output = self.gates[j].output;
+        self.gates[j].output = output;
+    }
+
+    // Whether the current gates pass more than the test vectors.
+    fn proven(&self) -> bool {
+        let puzzle = Puzzle {
+            fixed_wires: self.fixed_wires.to_vec(),
+            gates: self.gates.clone(),
+        };
+        matches!(prove(&puzzle), Ok(None))
+    }
+
+    // Depth-first search over swaps, each of which must push the lowest
+    // faulty bit higher.
+    fn run(&mut self, faulty: u128, left: usize) -> bool {
+        if faulty == 0 {
+            return self.proven();
+        }
+        if left == 0 {
+            return false;
+        }
+        let bit = faulty.trailing_zeros();
+        for i in self.suspects(bit as u8) {
+            for j in 0..self.gates.len() {
+                if i == j {
+                    continue;
+                }
+                self.swap(i, j);
+                if let Ok(mut circuit) = Circuit::from_parts(self.fixed_wires, &self.gates)
+                    && passes_through(&mut circuit, self.vectors, bit)
+                {
+                    let (a, b) = (self.gates[i].output, self.gates[j].output);
+                    self.swaps.push((a.min(b), a.max(b)));
+                    if self.run(faulty_bits(&mut circuit, self.vectors), left - 1) {
+                        return true;
+                    }
+                    self.swaps.pop();
+                }
+                self.swap(i, j);
+            }
+        }
+        false
+    }
+}
+
+// Find at most `max_swaps` pairs of gate outputs whose exchange makes the
+// netlist compute z = x + y. Candidates are found by simulation and then
+// proven equivalent to a ripple carry adder, so the search works whatever
+// adder architecture is used.
+pub fn repair(puzzle: &Puzzle, max_swaps: usize) -> Result<Vec<(Wire, Wire)>, RepairError> {
+    let mut circuit = Circuit::new(puzzle)?;
+    let vectors = test_vectors(circuit.width('x').max(circuit.width('y')), 64);
+    let faulty = faulty_bits(&mut circuit, &vectors);
+    let mut search = Search {
+        fixed_wires: &puzzle.fixed_wires,
+        vectors: &vectors,
+        gates: puzzle.gates.clone(),
+        swaps: Vec::new(),
+    };
+    if search.run(faulty, max_swaps) {
+        Ok(search.swaps)
+    } else {
+        Err(RepairError::NoRepair(max_swaps))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::circuit::ripple_carry_adder;
+    use crate::equivalence::test::majority_adder;
+    use crate::wire;
+
+    fn puzzle(gates: Vec<Gate>) -> Puzzle {
+        Puzzle {
+            fixed_wires: Vec::new(),
+            gates,
+        }
+    }
+
+    fn swapped(gates: Vec<Gate>, swaps: &[(Wire, Wire)]) -> Vec<Gate> {
+        let mut puzzle = puzzle(gates);
+        puzzle.apply_swaps(swaps).unwrap();
+        puzzle.gates
+    }
+
+    #[test]
+    fn test_vectors_cover_truth_table() {
+        let vectors = test_vectors(3, 0);
+        assert_eq!(vectors.len(), 1 + 3 + 7 + 7);
+        assert!(vectors.contains(&(0b110, 0b010)));
+        assert!(vectors.contains(&(0b011, 0b001)));
+    }
+
+    #[test]
+    fn test_correct_adder() {
+        let mut circuit = Circuit::from_parts(&[], &ripple_carry_adder(8)).unwrap();
+        assert_eq!(faulty_bits(&mut circuit, &test_vectors(8, 16)), 0);
+        assert_eq!(repair(&puzzle(ripple_carry_adder(8)), 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_faulty_bits() {
+        let gates = swapped(ripple_carry_adder(8), &[(wire!(z03), wire!(a03))]);
+        let mut circuit = Circuit::from_parts(&[], &gates).unwrap();
+        let faulty = faulty_bits(&mut circuit, &test_vectors(8, 16));
+        assert_eq!(faulty.trailing_zeros(), 3);
+    }
+
+    #[test]
+    fn test_repair() {
+        let swaps = [(wire!(a02), wire!(z02)), (wire!(b05), wire!(z05))];
+        let gates = swapped(ripple_carry_adder(8), &swaps);
+        assert_eq!(
+            repair(&puzzle(gates.clone()), 1),
+            Err(RepairError::NoRepair(1))
+        );
+        let mut found = repair(&puzzle(gates), 2).unwrap();
+        found.sort();
+        assert_eq!(found, swaps);
+    }
+
+    #[test]
+    fn test_repair_other_architecture() {
+        let gates = majority_adder(6).gates;
+        assert_eq!(repair(&puzzle(gates.clone()), 0), Ok(vec![]));
+        let swaps = [(wire!(q03), wire!(t03))];
+        let found = repair(&puzzle(swapped(gates, &swaps)), 2).unwrap();
+        assert_eq!(found, swaps);
+    }
+
+    #[test]
+    fn test_prove() {
+        assert_eq!(prove(&majority_adder(6)), Ok(None));
+        let broken = puzzle(swapped(ripple_carry_adder(6), &[(wire!(a02), wire!(z02))]));
+        assert_eq!(prove(&broken).unwrap().unwrap().wire, Wire::z(2));
+    }
+}
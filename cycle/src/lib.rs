@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The shape of the sequence `start, step(start), step(step(start)), ...`:
+/// state number `prefix` is the first one that repeats, it repeats every
+/// `period` steps, and its value is `entry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle<S> {
+    pub prefix: usize,
+    pub period: usize,
+    pub entry: S,
+}
+
+impl<S> Cycle<S> {
+    /// The smallest index whose state equals state number `n`.
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.prefix {
+            n
+        } else {
+            self.prefix + (n - self.prefix) % self.period
+        }
+    }
+}
+
+/// Floyd's tortoise and hare. Uses constant memory.
+pub fn floyd<S: Clone + Eq>(start: S, mut step: impl FnMut(S) -> S) -> Cycle<S> {
+    let mut tortoise = step(start.clone());
+    let mut hare = step(tortoise.clone());
+    while tortoise != hare {
+        tortoise = step(tortoise);
+        hare = step(hare);
+        hare = step(hare);
+    }
+    let mut prefix = 0;
+    tortoise = start;
+    while tortoise != hare {
+        tortoise = step(tortoise);
+        hare = step(hare);
+        prefix += 1;
+    }
+    let mut period = 1;
+    hare = step(tortoise.clone());
+    while tortoise != hare {
+        hare = step(hare);
+        period += 1;
+    }
+    Cycle {
+        prefix,
+        period,
+        entry: tortoise,
+    }
+}
+
+/// Brent's algorithm. Uses constant memory and usually fewer steps than
+/// [`floyd`].
+pub fn brent<S: Clone + Eq>(start: S, mut step: impl FnMut(S) -> S) -> Cycle<S> {
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start.clone());
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = step(hare);
+        period += 1;
+    }
+    tortoise = start.clone();
+    hare = start;
+    for _ in 0..period {
+        hare = step(hare);
+    }
+    let mut prefix = 0;
+    while tortoise != hare {
+        tortoise = step(tortoise);
+        hare = step(hare);
+        prefix += 1;
+    }
+    Cycle {
+        prefix,
+        period,
+        entry: tortoise,
+    }
+}
+
+/// Remembers every state. Calls `step` exactly once for each distinct state,
+/// in order, so it is safe to use with a step function that has side
+/// effects.
+pub fn hashed<S: Clone + Eq + Hash>(start: S, mut step: impl FnMut(S) -> S) -> Cycle<S> {
+    let mut seen: HashMap<S, usize> = HashMap::new();
+    let mut state = start;
+    for n in 0.. {
+        if let Some(&prefix) = seen.get(&state) {
+            return Cycle {
+                prefix,
+                period: n - prefix,
+                entry: state,
+            };
+        }
+        seen.insert(state.clone(), n);
+        state = step(state);
+    }
+    unreachable!()
+}
+
+/// State number `n` of the sequence, skipping ahead once a cycle is found.
+pub fn nth_state<S: Clone + Eq + Hash>(start: S, mut step: impl FnMut(S) -> S, n: usize) -> S {
+    let mut seen: HashMap<S, usize> = HashMap::new();
+    let mut history: Vec<S> = Vec::new();
+    let mut state = start;
+    for i in 0..n {
+        if let Some(&prefix) = seen.get(&state) {
+            let cycle = Cycle {
+                prefix,
+                period: i - prefix,
+                entry: (),
+            };
+            return history.swap_remove(cycle.reduce(n));
+        }
+        seen.insert(state.clone(), i);
+        history.push(state.clone());
+        state = step(state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 0, 1, 2, 3, 4, 5, 6, 7, 3, 4, ...
+    fn step(x: u32) -> u32 {
+        if x == 7 { 3 } else { x + 1 }
+    }
+
+    const EXPECTED: Cycle<u32> = Cycle {
+        prefix: 3,
+        period: 5,
+        entry: 3,
+    };
+
+    #[test]
+    fn test_floyd() {
+        assert_eq!(floyd(0, step), EXPECTED);
+    }
+
+    #[test]
+    fn test_brent() {
+        assert_eq!(brent(0, step), EXPECTED);
+    }
+
+    #[test]
+    fn test_hashed() {
+        assert_eq!(hashed(0, step), EXPECTED);
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let expected = Cycle {
+            prefix: 0,
+            period: 1,
+            entry: 0,
+        };
+        assert_eq!(floyd(0, |x| x), expected);
+        assert_eq!(brent(0, |x| x), expected);
+        assert_eq!(hashed(0, |x| x), expected);
+    }
+
+    #[test]
+    fn test_nth_state() {
+        let mut state = 0;
+        for n in 0..20 {
+            assert_eq!(nth_state(0, step, n), state, "n = {n}");
+            state = step(state);
+        }
+        assert_eq!(nth_state(0, step, 1_000_000_000_000), 5);
+    }
+}
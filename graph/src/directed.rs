@@ -0,0 +1,219 @@
+use crate::{Interner, NodeId, ParseError, parse_name};
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// A directed graph stored as successor lists, which keep the order (and
+/// any duplicates) of the edges as they were added.
+#[derive(Clone, Debug)]
+pub struct DiGraph<N> {
+    nodes: Interner<N>,
+    successors: Vec<Vec<NodeId>>,
+}
+
+#[derive(Debug)]
+pub struct CycleError;
+
+impl<N: Eq + Hash + Clone> DiGraph<N> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> &Interner<N> {
+        &self.nodes
+    }
+
+    pub fn add_node(&mut self, name: N) -> NodeId {
+        let id = self.nodes.intern(name);
+        if id == self.successors.len() {
+            self.successors.push(Vec::new());
+        }
+        id
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) -> (NodeId, NodeId) {
+        let (from, to) = (self.add_node(from), self.add_node(to));
+        self.successors[from].push(to);
+        (from, to)
+    }
+
+    pub fn id(&self, name: &N) -> Option<NodeId> {
+        self.nodes.id(name)
+    }
+
+    pub fn name(&self, id: NodeId) -> &N {
+        self.nodes.name(id)
+    }
+
+    pub fn successors(&self, id: NodeId) -> &[NodeId] {
+        &self.successors[id]
+    }
+
+    /// Tarjan's algorithm. Components are returned in reverse topological
+    /// order: no component has an edge into a later one.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        const UNVISITED: usize = usize::MAX;
+        let n = self.len();
+        let mut index = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+        for root in 0..n {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // Explicit call stack of (node, next successor to visit).
+            let mut calls = vec![(root, 0)];
+            index[root] = next_index;
+            low[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            while let Some(&(v, i)) = calls.last() {
+                if let Some(&w) = self.successors[v].get(i) {
+                    calls.last_mut().unwrap().1 += 1;
+                    if index[w] == UNVISITED {
+                        index[w] = next_index;
+                        low[w] = next_index;
+                        next_index += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        calls.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+                calls.pop();
+                if let Some(&(u, _)) = calls.last() {
+                    low[u] = low[u].min(low[v]);
+                }
+                if low[v] == index[v] {
+                    let mut component = Vec::new();
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Kahn's algorithm, preferring lower ids when there is a choice.
+    pub fn topological_sort(&self) -> Result<Vec<NodeId>, CycleError> {
+        let mut in_degree = vec![0usize; self.len()];
+        for &to in self.successors.iter().flatten() {
+            in_degree[to] += 1;
+        }
+        let mut ready: VecDeque<NodeId> = (0..self.len()).filter(|&v| in_degree[v] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(v) = ready.pop_front() {
+            order.push(v);
+            for &w in &self.successors[v] {
+                in_degree[w] -= 1;
+                if in_degree[w] == 0 {
+                    ready.push_back(w);
+                }
+            }
+        }
+        if order.len() == self.len() {
+            Ok(order)
+        } else {
+            Err(CycleError)
+        }
+    }
+}
+
+impl<N> Default for DiGraph<N> {
+    fn default() -> Self {
+        Self {
+            nodes: Interner::default(),
+            successors: Vec::new(),
+        }
+    }
+}
+
+/// Parses lines of the form `a -> b`, `a -> b, c` or `a = (b, c)`.
+impl<N: FromStr + Eq + Hash + Clone> FromStr for DiGraph<N> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut graph = Self::new();
+        for line in s.lines() {
+            let (from, to) = if let Some((from, to)) = line.split_once("->") {
+                (from, to)
+            } else {
+                let (from, to) = line.split_once('=').ok_or(ParseError)?;
+                let to = to.trim().strip_prefix('(').ok_or(ParseError)?;
+                (from, to.strip_suffix(')').ok_or(ParseError)?)
+            };
+            let from = graph.add_node(parse_name(from)?);
+            for to in to.split(',') {
+                let to = graph.add_node(parse_name(to)?);
+                graph.successors[from].push(to);
+            }
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let graph: DiGraph<String> = "AAA = (BBB, BBB)\nBBB -> AAA, CCC".parse().unwrap();
+        let [a, b, c] = ["AAA", "BBB", "CCC"].map(|name| graph.id(&name.to_string()).unwrap());
+        assert_eq!(graph.successors(a), [b, b]);
+        assert_eq!(graph.successors(b), [a, c]);
+        assert_eq!(graph.successors(c), []);
+    }
+
+    #[test]
+    fn test_scc() {
+        let graph: DiGraph<u32> = "1 -> 2\n2 -> 3\n3 -> 1\n3 -> 4\n4 -> 5\n5 -> 4"
+            .parse()
+            .unwrap();
+        let names: Vec<Vec<u32>> = graph
+            .strongly_connected_components()
+            .into_iter()
+            .map(|component| {
+                let mut names: Vec<u32> = component.into_iter().map(|v| *graph.name(v)).collect();
+                names.sort();
+                names
+            })
+            .collect();
+        assert_eq!(names, [vec![4, 5], vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let graph: DiGraph<u32> = "3 -> 1\n2 -> 1\n1 -> 0\n2 -> 3".parse().unwrap();
+        let order: Vec<u32> = graph
+            .topological_sort()
+            .unwrap()
+            .into_iter()
+            .map(|v| *graph.name(v))
+            .collect();
+        assert_eq!(order, [2, 3, 1, 0]);
+        let cyclic: DiGraph<u32> = "1 -> 2\n2 -> 1".parse().unwrap();
+        assert!(cyclic.topological_sort().is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::NodeId;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A bijection between names and dense [`NodeId`]s.
+#[derive(Clone, Debug)]
+pub struct Interner<N> {
+    names: Vec<N>,
+    ids: HashMap<N, NodeId>,
+}
+
+impl<N: Eq + Hash + Clone> Interner<N> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The id of `name`, allocating the next one if it is new.
+    pub fn intern(&mut self, name: N) -> NodeId {
+        if let Some(&id) = self.ids.get(&name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.ids.insert(name.clone(), id);
+        self.names.push(name);
+        id
+    }
+
+    pub fn id(&self, name: &N) -> Option<NodeId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: NodeId) -> &N {
+        &self.names[id]
+    }
+
+    pub fn names(&self) -> &[N] {
+        &self.names
+    }
+}
+
+impl<N> Default for Interner<N> {
+    fn default() -> Self {
+        Self {
+            names: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+}
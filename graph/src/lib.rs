@@ -0,0 +1,17 @@
+mod directed;
+mod interner;
+mod undirected;
+
+pub use directed::{CycleError, DiGraph};
+pub use interner::Interner;
+pub use undirected::{Cut, UnGraph};
+
+/// Index of a node in the order its name was first seen.
+pub type NodeId = usize;
+
+#[derive(Debug)]
+pub struct ParseError;
+
+fn parse_name<N: std::str::FromStr>(s: &str) -> Result<N, ParseError> {
+    s.trim().parse().map_err(|_| ParseError)
+}
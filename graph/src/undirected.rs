@@ -0,0 +1,298 @@
+use crate::{Interner, NodeId, ParseError, parse_name};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// An undirected graph stored as neighbour lists. Parallel edges are kept.
+#[derive(Clone, Debug)]
+pub struct UnGraph<N> {
+    nodes: Interner<N>,
+    neighbors: Vec<Vec<NodeId>>,
+}
+
+/// A set of nodes and the total weight of the edges leaving it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cut {
+    pub weight: usize,
+    pub nodes: Vec<NodeId>,
+}
+
+impl<N: Eq + Hash + Clone> UnGraph<N> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> &Interner<N> {
+        &self.nodes
+    }
+
+    pub fn add_node(&mut self, name: N) -> NodeId {
+        let id = self.nodes.intern(name);
+        if id == self.neighbors.len() {
+            self.neighbors.push(Vec::new());
+        }
+        id
+    }
+
+    pub fn add_edge(&mut self, a: N, b: N) -> (NodeId, NodeId) {
+        let (a, b) = (self.add_node(a), self.add_node(b));
+        self.neighbors[a].push(b);
+        if a != b {
+            self.neighbors[b].push(a);
+        }
+        (a, b)
+    }
+
+    pub fn id(&self, name: &N) -> Option<NodeId> {
+        self.nodes.id(name)
+    }
+
+    pub fn name(&self, id: NodeId) -> &N {
+        self.nodes.name(id)
+    }
+
+    pub fn neighbors(&self, id: NodeId) -> &[NodeId] {
+        &self.neighbors[id]
+    }
+
+    pub fn has_edge(&self, a: NodeId, b: NodeId) -> bool {
+        self.neighbors[a].contains(&b)
+    }
+
+    /// Nodes whose removal disconnects their component, in increasing order.
+    pub fn articulation_points(&self) -> Vec<NodeId> {
+        const UNVISITED: usize = usize::MAX;
+        let n = self.len();
+        let mut discovered = vec![UNVISITED; n];
+        let mut low = vec![0; n];
+        let mut parent = vec![UNVISITED; n];
+        let mut is_cut = vec![false; n];
+        let mut time = 0;
+        for root in 0..n {
+            if discovered[root] != UNVISITED {
+                continue;
+            }
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+            let mut root_children = 0;
+            // Explicit call stack of (node, next neighbour to visit).
+            let mut calls = vec![(root, 0)];
+            while let Some(&(v, i)) = calls.last() {
+                if let Some(&w) = self.neighbors[v].get(i) {
+                    calls.last_mut().unwrap().1 += 1;
+                    if discovered[w] == UNVISITED {
+                        parent[w] = v;
+                        discovered[w] = time;
+                        low[w] = time;
+                        time += 1;
+                        if v == root {
+                            root_children += 1;
+                        }
+                        calls.push((w, 0));
+                    } else if w != parent[v] {
+                        low[v] = low[v].min(discovered[w]);
+                    }
+                    continue;
+                }
+                calls.pop();
+                if let Some(&(u, _)) = calls.last() {
+                    low[u] = low[u].min(low[v]);
+                    if u != root && low[v] >= discovered[u] {
+                        is_cut[u] = true;
+                    }
+                }
+            }
+            is_cut[root] = root_children > 1;
+        }
+        (0..n).filter(|&v| is_cut[v]).collect()
+    }
+
+    /// The global minimum cut, counting each edge as weight 1, found with
+    /// the Stoer–Wagner algorithm. `None` if there are fewer than two nodes.
+    pub fn min_cut(&self) -> Option<Cut> {
+        let n = self.len();
+        if n < 2 {
+            return None;
+        }
+        let mut weights: Vec<HashMap<NodeId, usize>> = vec![HashMap::new(); n];
+        for (a, neighbors) in self.neighbors.iter().enumerate() {
+            for &b in neighbors.iter().filter(|&&b| b != a) {
+                *weights[a].entry(b).or_default() += 1;
+            }
+        }
+        let mut members: Vec<Vec<NodeId>> = (0..n).map(|v| vec![v]).collect();
+        let mut active: Vec<NodeId> = (0..n).collect();
+        let mut best: Option<Cut> = None;
+        while active.len() > 1 {
+            // Maximum adjacency ordering; the last two nodes added are s and t.
+            let mut added = vec![false; n];
+            let mut connectivity = vec![0usize; n];
+            let mut heap: BinaryHeap<(usize, NodeId)> = active.iter().map(|&v| (0, v)).collect();
+            let (mut s, mut t) = (active[0], active[0]);
+            while let Some((weight, v)) = heap.pop() {
+                if added[v] || weight != connectivity[v] {
+                    continue;
+                }
+                added[v] = true;
+                (s, t) = (t, v);
+                for (&u, &w) in &weights[v] {
+                    if !added[u] {
+                        connectivity[u] += w;
+                        heap.push((connectivity[u], u));
+                    }
+                }
+            }
+            if best
+                .as_ref()
+                .is_none_or(|best| connectivity[t] < best.weight)
+            {
+                best = Some(Cut {
+                    weight: connectivity[t],
+                    nodes: members[t].clone(),
+                });
+            }
+            // Merge t into s.
+            for (u, w) in std::mem::take(&mut weights[t]) {
+                weights[u].remove(&t);
+                if u != s {
+                    *weights[s].entry(u).or_default() += w;
+                    *weights[u].entry(s).or_default() += w;
+                }
+            }
+            let merged = std::mem::take(&mut members[t]);
+            members[s].extend(merged);
+            active.retain(|&v| v != t);
+        }
+        best
+    }
+
+    /// All maximal cliques, found with the Bron–Kerbosch algorithm with
+    /// pivoting.
+    pub fn maximal_cliques(&self) -> Vec<Vec<NodeId>> {
+        let adjacent: Vec<HashSet<NodeId>> = self
+            .neighbors
+            .iter()
+            .enumerate()
+            .map(|(v, neighbors)| neighbors.iter().copied().filter(|&w| w != v).collect())
+            .collect();
+        let mut cliques = Vec::new();
+        bron_kerbosch(
+            &adjacent,
+            &mut Vec::new(),
+            (0..self.len()).collect(),
+            HashSet::new(),
+            &mut cliques,
+        );
+        cliques
+    }
+}
+
+fn bron_kerbosch(
+    adjacent: &[HashSet<NodeId>],
+    clique: &mut Vec<NodeId>,
+    mut candidates: HashSet<NodeId>,
+    mut excluded: HashSet<NodeId>,
+    cliques: &mut Vec<Vec<NodeId>>,
+) {
+    let Some(pivot) = candidates
+        .union(&excluded)
+        .max_by_key(|&&v| adjacent[v].intersection(&candidates).count())
+        .copied()
+    else {
+        cliques.push(clique.clone());
+        return;
+    };
+    let branches: Vec<NodeId> = candidates.difference(&adjacent[pivot]).copied().collect();
+    for v in branches {
+        clique.push(v);
+        bron_kerbosch(
+            adjacent,
+            clique,
+            candidates.intersection(&adjacent[v]).copied().collect(),
+            excluded.intersection(&adjacent[v]).copied().collect(),
+            cliques,
+        );
+        clique.pop();
+        candidates.remove(&v);
+        excluded.insert(v);
+    }
+}
+
+impl<N> Default for UnGraph<N> {
+    fn default() -> Self {
+        Self {
+            nodes: Interner::default(),
+            neighbors: Vec::new(),
+        }
+    }
+}
+
+/// Parses lines of the form `a-b`.
+impl<N: FromStr + Eq + Hash + Clone> FromStr for UnGraph<N> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut graph = Self::new();
+        for line in s.lines() {
+            let (a, b) = line.split_once('-').ok_or(ParseError)?;
+            graph.add_edge(parse_name(a)?, parse_name(b)?);
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(graph: &UnGraph<String>, ids: &[NodeId]) -> Vec<String> {
+        let mut names: Vec<String> = ids.iter().map(|&v| graph.name(v).clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_articulation_points() {
+        // Two triangles joined through c-d, plus a pendant e.
+        let graph: UnGraph<String> = "a-b\nb-c\nc-a\nc-d\nd-f\nf-g\ng-d\ng-e".parse().unwrap();
+        assert_eq!(names(&graph, &graph.articulation_points()), ["c", "d", "g"]);
+    }
+
+    #[test]
+    fn test_min_cut() {
+        // Two 4-cliques joined by two edges.
+        let graph: UnGraph<String> = "a-b\na-c\na-d\nb-c\nb-d\nc-d\n\
+                                      e-f\ne-g\ne-h\nf-g\nf-h\ng-h\n\
+                                      a-e\nb-f"
+            .parse()
+            .unwrap();
+        let cut = graph.min_cut().unwrap();
+        assert_eq!(cut.weight, 2);
+        let side = names(&graph, &cut.nodes);
+        assert!(side == ["a", "b", "c", "d"] || side == ["e", "f", "g", "h"]);
+    }
+
+    #[test]
+    fn test_maximal_cliques() {
+        let graph: UnGraph<String> = "a-b\na-c\nb-c\nc-d\nd-e".parse().unwrap();
+        let mut cliques: Vec<Vec<String>> = graph
+            .maximal_cliques()
+            .iter()
+            .map(|clique| names(&graph, clique))
+            .collect();
+        cliques.sort();
+        assert_eq!(
+            cliques,
+            [vec!["a", "b", "c"], vec!["c", "d"], vec!["d", "e"]]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod map;
+mod set;
+
+pub use map::{OverlapError, PiecewiseMap};
+pub use set::IntervalSet;
@@ -0,0 +1,262 @@
+use crate::IntervalSet;
+use std::ops::{Add, Range, Sub};
+
+/// A function that shifts each of a number of disjoint source ranges to a
+/// destination and leaves every other value unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PiecewiseMap<T> {
+    // Sorted by source, non-overlapping, with no identity pieces.
+    pieces: Vec<Piece<T>>,
+}
+
+/// The source range given to [`PiecewiseMap::insert`] overlaps one that is
+/// already mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlapError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Piece<T> {
+    source: Range<T>,
+    dest: T,
+}
+
+impl<T> Piece<T>
+where
+    T: Copy + Ord + Add<Output = T> + Sub<Output = T>,
+{
+    fn len(&self) -> T {
+        self.source.end - self.source.start
+    }
+
+    fn is_identity(&self) -> bool {
+        self.dest == self.source.start
+    }
+
+    fn continues(&self, next: &Self) -> bool {
+        self.source.end == next.source.start && self.dest + self.len() == next.dest
+    }
+}
+
+impl<T> PiecewiseMap<T>
+where
+    T: Copy + Ord + Add<Output = T> + Sub<Output = T>,
+{
+    /// The identity map.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn from_pieces(mut pieces: Vec<Piece<T>>) -> Self {
+        pieces.retain(|piece| !piece.source.is_empty() && !piece.is_identity());
+        pieces.sort_by_key(|piece| piece.source.start);
+        let mut merged: Vec<Piece<T>> = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            match merged.last_mut() {
+                Some(last) if last.continues(&piece) => last.source.end = piece.source.end,
+                _ => merged.push(piece),
+            }
+        }
+        Self { pieces: merged }
+    }
+
+    /// Maps `source` onto `dest..dest + source.len()`, merging it with any
+    /// neighbouring piece that it continues so that equal functions compare
+    /// equal.
+    ///
+    /// Fails, leaving the map unchanged, if `source` overlaps a range that is
+    /// already mapped.
+    pub fn insert(&mut self, source: Range<T>, dest: T) -> Result<(), OverlapError> {
+        let piece = Piece { source, dest };
+        if piece.source.is_empty() {
+            return Ok(());
+        }
+        let idx = self
+            .pieces
+            .partition_point(|p| p.source.end <= piece.source.start);
+        if let Some(next) = self.pieces.get(idx)
+            && next.source.start < piece.source.end
+        {
+            return Err(OverlapError);
+        }
+        if piece.is_identity() {
+            return Ok(());
+        }
+        let merges_prev = idx > 0 && self.pieces[idx - 1].continues(&piece);
+        let merges_next = self
+            .pieces
+            .get(idx)
+            .is_some_and(|next| piece.continues(next));
+        match (merges_prev, merges_next) {
+            (true, true) => {
+                let next = self.pieces.remove(idx);
+                self.pieces[idx - 1].source.end = next.source.end;
+            }
+            (true, false) => self.pieces[idx - 1].source.end = piece.source.end,
+            (false, true) => {
+                let next = &mut self.pieces[idx];
+                next.source.start = piece.source.start;
+                next.dest = piece.dest;
+            }
+            (false, false) => self.pieces.insert(idx, piece),
+        }
+        Ok(())
+    }
+
+    /// The mapped source ranges with the destination of their first value.
+    pub fn pieces(&self) -> impl Iterator<Item = (Range<T>, T)> + '_ {
+        self.pieces
+            .iter()
+            .map(|piece| (piece.source.clone(), piece.dest))
+    }
+
+    pub fn apply(&self, value: T) -> T {
+        let idx = self.pieces.partition_point(|p| p.source.end <= value);
+        match self.pieces.get(idx) {
+            Some(piece) if piece.source.start <= value => piece.dest + (value - piece.source.start),
+            _ => value,
+        }
+    }
+
+    /// Splits `range` into maximal pieces that are each shifted by a single
+    /// offset, yielding each piece with the image of its first value.
+    pub fn segments(&self, range: Range<T>) -> impl Iterator<Item = (Range<T>, T)> + '_ {
+        let Range { mut start, end } = range;
+        let mut idx = self.pieces.partition_point(|p| p.source.end <= start);
+        std::iter::from_fn(move || {
+            if start >= end {
+                return None;
+            }
+            let (seg_end, dest) = match self.pieces.get(idx) {
+                Some(piece) if piece.source.start <= start => {
+                    idx += 1;
+                    (
+                        piece.source.end.min(end),
+                        piece.dest + (start - piece.source.start),
+                    )
+                }
+                Some(piece) => (piece.source.start.min(end), start),
+                None => (end, start),
+            };
+            let segment = start..seg_end;
+            start = seg_end;
+            Some((segment, dest))
+        })
+    }
+
+    /// The image of every value in `set`.
+    pub fn image(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        set.iter()
+            .flat_map(|range| self.segments(range))
+            .map(|(segment, dest)| dest..dest + (segment.end - segment.start))
+            .collect()
+    }
+
+    /// The map `x -> next.apply(self.apply(x))`.
+    pub fn then(&self, next: &Self) -> Self {
+        let mut pieces = Vec::new();
+        for piece in &self.pieces {
+            for (segment, dest) in next.segments(piece.dest..piece.dest + piece.len()) {
+                let start = piece.source.start + (segment.start - piece.dest);
+                pieces.push(Piece {
+                    source: start..start + (segment.end - segment.start),
+                    dest,
+                });
+            }
+        }
+        // Values outside our pieces reach `next` unchanged.
+        let domain: IntervalSet<T> = self.pieces.iter().map(|p| p.source.clone()).collect();
+        for piece in &next.pieces {
+            let outside = IntervalSet::from_iter([piece.source.clone()]).difference(&domain);
+            pieces.extend(outside.into_iter().map(|source| Piece {
+                dest: piece.dest + (source.start - piece.source.start),
+                source,
+            }));
+        }
+        Self::from_pieces(pieces)
+    }
+}
+
+impl<T> Default for PiecewiseMap<T> {
+    fn default() -> Self {
+        Self { pieces: Vec::new() }
+    }
+}
+
+/// Panics if two of the source ranges overlap; use
+/// [`PiecewiseMap::insert`] to handle that case.
+impl<T> FromIterator<(Range<T>, T)> for PiecewiseMap<T>
+where
+    T: Copy + Ord + Add<Output = T> + Sub<Output = T>,
+{
+    fn from_iter<Iter: IntoIterator<Item = (Range<T>, T)>>(iter: Iter) -> Self {
+        let mut this = Self::new();
+        for (source, dest) in iter {
+            this.insert(source, dest)
+                .expect("overlapping source ranges");
+        }
+        this
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let map: PiecewiseMap<u32> = [(98..100, 50), (50..98, 52)].into_iter().collect();
+        assert_eq!(map.apply(79), 81);
+        assert_eq!(map.apply(99), 51);
+        assert_eq!(map.apply(14), 14);
+    }
+
+    #[test]
+    fn test_image() {
+        let map: PiecewiseMap<u32> = [(98..100, 50), (50..98, 52)].into_iter().collect();
+        let set: IntervalSet<u32> = [45..55, 97..101].into_iter().collect();
+        let expected: IntervalSet<u32> = [45..50, 52..57, 99..101, 50..52, 100..101]
+            .into_iter()
+            .collect();
+        assert_eq!(map.image(&set), expected);
+    }
+
+    #[test]
+    fn test_then() {
+        let f: PiecewiseMap<u32> = [(0..10, 20), (30..40, 0)].into_iter().collect();
+        let g: PiecewiseMap<u32> = [(5..25, 100), (35..45, 1)].into_iter().collect();
+        let h = f.then(&g);
+        for x in 0..60 {
+            assert_eq!(h.apply(x), g.apply(f.apply(x)), "x = {x}");
+        }
+    }
+
+    #[test]
+    fn test_then_inverse_is_identity() {
+        let f: PiecewiseMap<u32> = [(0..10, 10), (10..20, 0)].into_iter().collect();
+        assert_eq!(f.then(&f), PiecewiseMap::new());
+    }
+
+    #[test]
+    fn test_insert_overlap() {
+        let mut map: PiecewiseMap<u32> = [(10..20, 50)].into_iter().collect();
+        assert_eq!(map.insert(15..25, 0), Err(OverlapError));
+        assert_eq!(map.insert(5..11, 0), Err(OverlapError));
+        assert_eq!(map.insert(12..14, 12), Err(OverlapError));
+        assert_eq!(map, [(10..20, 50)].into_iter().collect());
+    }
+
+    #[test]
+    fn test_insert_merges() {
+        let map: PiecewiseMap<u32> = [(0..5, 100), (10..15, 110), (5..10, 105)]
+            .into_iter()
+            .collect();
+        assert_eq!(map.pieces().collect::<Vec<_>>(), [(0..15, 100)]);
+        let mut map: PiecewiseMap<u32> = [(5..10, 105)].into_iter().collect();
+        map.insert(0..5, 100).unwrap();
+        map.insert(10..12, 0).unwrap();
+        assert_eq!(
+            map.pieces().collect::<Vec<_>>(),
+            [(0..10, 100), (10..12, 0)]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::ops::Range;
+
+/// A set of values stored as sorted, disjoint, non-adjacent half-open ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalSet<T>(Vec<Range<T>>);
+
+pub type IntoIter<T> = std::vec::IntoIter<Range<T>>;
+
+impl<T: Copy + Ord> IntervalSet<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.0.iter().cloned()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.0.first().map(|range| range.start)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let idx = self.0.partition_point(|range| range.end <= value);
+        self.0.get(idx).is_some_and(|range| range.start <= value)
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.is_empty() {
+            return;
+        }
+        // Every range in lo..hi overlaps or touches the new one.
+        let lo = self.0.partition_point(|r| r.end < range.start);
+        let hi = self.0.partition_point(|r| r.start <= range.end);
+        let (start, end) = if lo < hi {
+            (
+                self.0[lo].start.min(range.start),
+                self.0[hi - 1].end.max(range.end),
+            )
+        } else {
+            (range.start, range.end)
+        };
+        self.0.splice(lo..hi, [start..end]);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.extend(other.iter());
+        result
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (a, b) = (&self.0, &other.0);
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            let start = a[i].start.max(b[j].start);
+            let end = a[i].end.min(b[j].end);
+            if start < end {
+                result.push(start..end);
+            }
+            if a[i].end < b[j].end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self(result)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let b = &other.0;
+        let mut result = Vec::new();
+        let mut j = 0;
+        for range in &self.0 {
+            let mut start = range.start;
+            while j < b.len() && b[j].end <= start {
+                j += 1;
+            }
+            for hole in b[j..].iter().take_while(|hole| hole.start < range.end) {
+                if start < hole.start {
+                    result.push(start..hole.start);
+                }
+                start = start.max(hole.end);
+            }
+            if start < range.end {
+                result.push(start..range.end);
+            }
+        }
+        Self(result)
+    }
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: Copy + Ord> Extend<Range<T>> for IntervalSet<T> {
+    fn extend<Iter: IntoIterator<Item = Range<T>>>(&mut self, iter: Iter) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<Iter: IntoIterator<Item = Range<T>>>(iter: Iter) -> Self {
+        let mut this = Self::new();
+        this.extend(iter);
+        this
+    }
+}
+
+impl<T> IntoIterator for IntervalSet<T> {
+    type Item = Range<T>;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn set(ranges: &[(u32, u32)]) -> IntervalSet<u32> {
+        ranges.iter().map(|&(start, end)| start..end).collect()
+    }
+
+    #[test]
+    fn test_insert_coalesces() {
+        let s = set(&[(5, 7), (0, 2), (2, 3), (10, 12), (6, 11)]);
+        assert_eq!(s.iter().collect::<Vec<_>>(), [0..3, 5..12]);
+        assert!(s.contains(11));
+        assert!(!s.contains(3));
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(
+            set(&[(0, 2), (8, 9)]).union(&set(&[(1, 4)])),
+            set(&[(0, 4), (8, 9)])
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            set(&[(0, 5), (10, 15)]).intersection(&set(&[(3, 12), (14, 20)])),
+            set(&[(3, 5), (10, 12), (14, 15)])
+        );
+    }
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(
+            set(&[(0, 10), (20, 30)]).difference(&set(&[(2, 4), (6, 22), (29, 40)])),
+            set(&[(0, 2), (4, 6), (22, 29)])
+        );
+    }
+}
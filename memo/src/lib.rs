@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A cache for the results of a recursive function.
+///
+/// The function is passed as a plain `fn` so that it can name itself. It
+/// receives the cache back along with a context argument, and recurses by
+/// calling [`Memo::get`] on it.
+#[derive(Clone, Debug)]
+pub struct Memo<K, V>(HashMap<K, V>);
+
+impl<K: Eq + Hash + Clone, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get<C: ?Sized>(&mut self, context: &C, key: K, f: fn(&mut Self, &C, K) -> V) -> V {
+        if let Some(value) = self.0.get(&key) {
+            return value.clone();
+        }
+        let value = f(self, context, key.clone());
+        self.0.insert(key, value.clone());
+        value
+    }
+
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> V {
+        self.0.entry(key).or_insert_with(f).clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl<K, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn paths(memo: &mut Memo<(u32, u32), u64>, blocked: &[(u32, u32)], (x, y): (u32, u32)) -> u64 {
+        if blocked.contains(&(x, y)) {
+            0
+        } else if x == 0 || y == 0 {
+            1
+        } else {
+            memo.get(blocked, (x - 1, y), paths) + memo.get(blocked, (x, y - 1), paths)
+        }
+    }
+
+    #[test]
+    fn test_recursive() {
+        let mut memo = Memo::new();
+        assert_eq!(memo.get(&[][..], (16, 16), paths), 601080390);
+        assert_eq!(memo.len(), 17 * 17 - 1);
+        memo.clear();
+        assert!(memo.is_empty());
+        assert_eq!(memo.get(&[(1, 1)][..], (2, 2), paths), 2);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut memo = Memo::new();
+        assert_eq!(memo.get_or_insert_with("a", || 1), 1);
+        assert_eq!(memo.get_or_insert_with("a", || 2), 1);
+        assert_eq!(memo.len(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Aggregate, UnionFind};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Disjoint sets of hashable keys, backed by a dense [`UnionFind`].
+#[derive(Clone, Debug)]
+pub struct HashUnionFind<K, A = ()> {
+    index: HashMap<K, usize>,
+    keys: Vec<K>,
+    sets: UnionFind<A>,
+}
+
+impl<K: Eq + Hash + Clone, A: Aggregate> HashUnionFind<K, A> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Adds `key` as a singleton component. Returns `false`, leaving the
+    /// existing component unchanged, if `key` was already present.
+    pub fn insert(&mut self, key: K, aggregate: A) -> bool {
+        if self.index.contains_key(&key) {
+            return false;
+        }
+        let index = self.sets.push(aggregate);
+        self.index.insert(key.clone(), index);
+        self.keys.push(key);
+        true
+    }
+
+    fn index_of(&self, key: &K) -> usize {
+        *self.index.get(key).expect("unknown key")
+    }
+
+    /// The representative key of the component containing `key`.
+    pub fn find(&mut self, key: &K) -> &K {
+        let root = self.sets.find(self.index_of(key));
+        &self.keys[root]
+    }
+
+    /// Joins the components containing `a` and `b`. Returns `false` if they
+    /// were already the same component.
+    pub fn union(&mut self, a: &K, b: &K) -> bool {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.sets.union(a, b)
+    }
+
+    pub fn same(&mut self, a: &K, b: &K) -> bool {
+        let (a, b) = (self.index_of(a), self.index_of(b));
+        self.sets.same(a, b)
+    }
+
+    pub fn size(&mut self, key: &K) -> usize {
+        self.sets.size(self.index_of(key))
+    }
+
+    pub fn aggregate(&mut self, key: &K) -> &A {
+        self.sets.aggregate(self.index_of(key))
+    }
+
+    /// The representative key, size and aggregate of every component.
+    pub fn components(&self) -> impl Iterator<Item = (&K, usize, &A)> {
+        self.sets
+            .components()
+            .map(|(root, size, aggregate)| (&self.keys[root], size, aggregate))
+    }
+
+    /// The keys of every component.
+    pub fn sets(&mut self) -> impl Iterator<Item = Vec<K>> + use<K, A> {
+        let mut sets: HashMap<usize, Vec<K>> = HashMap::new();
+        for (index, key) in self.keys.iter().enumerate() {
+            let root = self.sets.find(index);
+            sets.entry(root).or_default().push(key.clone());
+        }
+        sets.into_values()
+    }
+}
+
+impl<K, A> Default for HashUnionFind<K, A> {
+    fn default() -> Self {
+        Self {
+            index: HashMap::new(),
+            keys: Vec::new(),
+            sets: UnionFind::default(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> FromIterator<(K, K)> for HashUnionFind<K> {
+    fn from_iter<Iter: IntoIterator<Item = (K, K)>>(iter: Iter) -> Self {
+        let mut this = Self::new();
+        for (a, b) in iter {
+            this.insert(a.clone(), ());
+            this.insert(b.clone(), ());
+            this.union(&a, &b);
+        }
+        this
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let mut uf: HashUnionFind<&str> =
+            [("a", "b"), ("c", "d"), ("b", "e")].into_iter().collect();
+        uf.insert("f", ());
+        assert!(uf.same(&"a", &"e"));
+        assert!(!uf.same(&"a", &"c"));
+        assert_eq!(uf.size(&"e"), 3);
+        let mut sets: Vec<Vec<&str>> = uf
+            .sets()
+            .map(|mut set| {
+                set.sort();
+                set
+            })
+            .collect();
+        sets.sort();
+        assert_eq!(sets, [vec!["a", "b", "e"], vec!["c", "d"], vec!["f"]]);
+    }
+}
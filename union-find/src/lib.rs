@@ -0,0 +1,195 @@
+mod keyed;
+
+pub use keyed::HashUnionFind;
+
+/// Per-component data that is combined when two components are joined.
+pub trait Aggregate {
+    fn merge(&mut self, other: Self);
+}
+
+impl Aggregate for () {
+    fn merge(&mut self, _other: Self) {}
+}
+
+impl<A: Aggregate, B: Aggregate> Aggregate for (A, B) {
+    fn merge(&mut self, other: Self) {
+        self.0.merge(other.0);
+        self.1.merge(other.1);
+    }
+}
+
+/// Disjoint sets over the indices `0..len()`, with path compression and
+/// union by rank.
+#[derive(Clone, Debug)]
+pub struct UnionFind<A = ()> {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    // Only meaningful at the root of each component.
+    aggregate: Vec<Option<A>>,
+}
+
+impl UnionFind {
+    /// `len` singleton components.
+    pub fn with_len(len: usize) -> Self {
+        std::iter::repeat_n((), len).collect()
+    }
+}
+
+impl<A: Aggregate> UnionFind<A> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Adds a singleton component and returns its index.
+    pub fn push(&mut self, aggregate: A) -> usize {
+        let index = self.parent.len();
+        self.parent.push(index);
+        self.rank.push(0);
+        self.size.push(1);
+        self.aggregate.push(Some(aggregate));
+        index
+    }
+
+    /// The root of the component containing `index`.
+    pub fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut index = index;
+        while self.parent[index] != root {
+            index = std::mem::replace(&mut self.parent[index], root);
+        }
+        root
+    }
+
+    /// Joins the components containing `a` and `b`. Returns `false` if they
+    /// were already the same component.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.rank[a] < self.rank[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        if self.rank[a] == self.rank[b] {
+            self.rank[a] += 1;
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        let other = self.aggregate[b].take().unwrap();
+        self.aggregate[a].as_mut().unwrap().merge(other);
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of elements in the component containing `index`.
+    pub fn size(&mut self, index: usize) -> usize {
+        let root = self.find(index);
+        self.size[root]
+    }
+
+    pub fn aggregate(&mut self, index: usize) -> &A {
+        let root = self.find(index);
+        self.aggregate[root].as_ref().unwrap()
+    }
+
+    /// The root, size and aggregate of every component.
+    pub fn components(&self) -> impl Iterator<Item = (usize, usize, &A)> {
+        self.aggregate
+            .iter()
+            .enumerate()
+            .filter_map(|(root, aggregate)| Some((root, self.size[root], aggregate.as_ref()?)))
+    }
+
+    pub fn into_aggregates(self) -> impl Iterator<Item = A> {
+        self.aggregate.into_iter().flatten()
+    }
+}
+
+impl<A> Default for UnionFind<A> {
+    fn default() -> Self {
+        Self {
+            parent: Vec::new(),
+            rank: Vec::new(),
+            size: Vec::new(),
+            aggregate: Vec::new(),
+        }
+    }
+}
+
+impl<A: Aggregate> Extend<A> for UnionFind<A> {
+    fn extend<Iter: IntoIterator<Item = A>>(&mut self, iter: Iter) {
+        for aggregate in iter {
+            self.push(aggregate);
+        }
+    }
+}
+
+impl<A: Aggregate> FromIterator<A> for UnionFind<A> {
+    fn from_iter<Iter: IntoIterator<Item = A>>(iter: Iter) -> Self {
+        let mut this = Self::new();
+        this.extend(iter);
+        this
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct BoundingBox {
+        min: i32,
+        max: i32,
+    }
+
+    impl Aggregate for BoundingBox {
+        fn merge(&mut self, other: Self) {
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+        }
+    }
+
+    #[test]
+    fn test_sizes() {
+        let mut uf = UnionFind::with_len(6);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 1));
+        assert!(!uf.union(0, 2));
+        assert!(uf.union(4, 5));
+        assert!(uf.same(0, 2));
+        assert!(!uf.same(0, 3));
+        assert_eq!(uf.size(1), 3);
+        let mut sizes: Vec<usize> = uf.components().map(|(_, size, _)| size).collect();
+        sizes.sort();
+        assert_eq!(sizes, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let mut uf: UnionFind<BoundingBox> = [5, -2, 7, 3]
+            .into_iter()
+            .map(|x| BoundingBox { min: x, max: x })
+            .collect();
+        uf.union(0, 2);
+        uf.union(1, 3);
+        assert_eq!(*uf.aggregate(2), BoundingBox { min: 5, max: 7 });
+        uf.union(3, 0);
+        assert_eq!(*uf.aggregate(1), BoundingBox { min: -2, max: 7 });
+        assert_eq!(uf.components().count(), 1);
+    }
+}
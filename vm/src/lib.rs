@@ -0,0 +1,168 @@
+use bytecode::{ByteCode, DecodeError};
+use std::collections::VecDeque;
+
+// What the machine does after executing an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Next,
+    Jump(usize),
+    Halt,
+    // Stops `run` after this instruction.
+    Yield,
+    // The instruction couldn't run for lack of input; it runs again once
+    // there's more.
+    Block,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status<W = u8> {
+    Running,
+    Halted,
+    Yielded,
+    Blocked,
+    Fault(DecodeError<W>),
+}
+
+// An instruction set, executing against a machine with registers `R` and
+// memory `M` made of words `W`.
+pub trait Execute<R, M, W: 'static = u8>: ByteCode<W> {
+    fn execute(&self, vm: &mut Vm<R, M, W>) -> Effect;
+}
+
+pub struct Vm<R, M, W = u8> {
+    pub registers: R,
+    pub memory: M,
+    pub pc: usize,
+    pub input: VecDeque<W>,
+    pub output: VecDeque<W>,
+    status: Status<W>,
+}
+
+impl<R, M: AsRef<[W]>, W: Copy + PartialEq + 'static> Vm<R, M, W> {
+    pub fn new(registers: R, memory: M) -> Self {
+        Self {
+            registers,
+            memory,
+            pc: 0,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            status: Status::Running,
+        }
+    }
+
+    pub fn status(&self) -> Status<W> {
+        self.status
+    }
+
+    // The instruction at the program counter. Running off the end of memory
+    // halts the machine.
+    pub fn fetch<I: ByteCode<W>>(&self) -> Result<(I, usize), Status<W>> {
+        let memory = self.memory.as_ref();
+        match I::decode(memory.get(self.pc..).unwrap_or_default()) {
+            Ok(decoded) => Ok(decoded),
+            Err(DecodeError::Empty | DecodeError::Truncated { .. }) => Err(Status::Halted),
+            Err(err) => Err(Status::Fault(err)),
+        }
+    }
+
+    // Executes one instruction and returns it, or returns why it couldn't.
+    pub fn step<I: Execute<R, M, W>>(&mut self) -> Result<I, Status<W>> {
+        if let Status::Halted | Status::Fault(_) = self.status {
+            return Err(self.status);
+        }
+        let (instruction, width) = self
+            .fetch::<I>()
+            .inspect_err(|&status| self.status = status)?;
+        self.status = Status::Running;
+        match instruction.execute(self) {
+            Effect::Next => self.pc += width,
+            Effect::Jump(target) => self.pc = target,
+            Effect::Halt => self.status = Status::Halted,
+            Effect::Yield => {
+                self.pc += width;
+                self.status = Status::Yielded;
+            }
+            Effect::Block => {
+                self.status = Status::Blocked;
+                return Err(self.status);
+            }
+        }
+        Ok(instruction)
+    }
+
+    // Runs until the machine halts, faults, yields, or blocks on input.
+    pub fn run<I: Execute<R, M, W>>(&mut self) -> Status<W> {
+        while self.step::<I>().is_ok() && self.status == Status::Running {}
+        self.status
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytecode_derive::ByteCode;
+
+    // An accumulator machine with self-modifying code.
+    #[derive(ByteCode, Clone, Copy, Debug, PartialEq)]
+    #[bytecode(word = i64)]
+    enum Acc {
+        #[bytecode(opcode = 0)]
+        Halt,
+        #[bytecode(opcode = 1)]
+        Add(i64),
+        #[bytecode(opcode = 2)]
+        In,
+        #[bytecode(opcode = 3)]
+        Out,
+        #[bytecode(opcode = 4)]
+        Jnz(i64),
+        #[bytecode(opcode = 5)]
+        Yield,
+        #[bytecode(opcode = 6)]
+        Store(i64),
+    }
+
+    impl Execute<i64, Vec<i64>, i64> for Acc {
+        fn execute(&self, vm: &mut Vm<i64, Vec<i64>, i64>) -> Effect {
+            match *self {
+                Acc::Halt => return Effect::Halt,
+                Acc::Add(n) => vm.registers += n,
+                Acc::In => match vm.input.pop_front() {
+                    Some(value) => vm.registers = value,
+                    None => return Effect::Block,
+                },
+                Acc::Out => vm.output.push_back(vm.registers),
+                Acc::Jnz(target) if vm.registers != 0 => return Effect::Jump(target as usize),
+                Acc::Jnz(_) => (),
+                Acc::Yield => return Effect::Yield,
+                Acc::Store(address) => vm.memory[address as usize] = vm.registers,
+            }
+            Effect::Next
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        // Counts down from its input, yielding after each output.
+        let program = vec![2, 3, 5, 1, -1, 4, 1, 0];
+        let mut vm = Vm::new(0, program);
+        assert_eq!(vm.run::<Acc>(), Status::Blocked);
+        assert_eq!(vm.pc, 0);
+        vm.input.push_back(2);
+        assert_eq!(vm.run::<Acc>(), Status::Yielded);
+        assert_eq!(vm.run::<Acc>(), Status::Yielded);
+        assert_eq!(vm.run::<Acc>(), Status::Halted);
+        assert_eq!(vm.run::<Acc>(), Status::Halted);
+        assert_eq!(vm.output, [2, 1]);
+    }
+
+    #[test]
+    fn test_faults() {
+        // Overwrites its own halt with an invalid opcode.
+        let mut vm = Vm::new(0, vec![1, 9, 6, 4, 0]);
+        assert_eq!(vm.run::<Acc>(), Status::Fault(DecodeError::Opcode(9)));
+        assert_eq!(vm.pc, 4);
+        let mut vm = Vm::new(0, vec![1]);
+        assert_eq!(vm.step::<Acc>(), Err(Status::Halted));
+    }
+}